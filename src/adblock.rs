@@ -0,0 +1,426 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::lines::read_lines;
+#[cfg(feature = "regex")]
+use crate::{normalize, regex_rules, strip_root};
+use crate::{DomainLookupBuilder, DomainLookupMap, PatternError, Verdict};
+
+/// Markers of cosmetic and scriptlet filters, which act on page content rather than domains.
+const COSMETIC_MARKERS: &[&str] = &["##", "#@#", "#?#", "#$#", "#%#"];
+
+/// Why a filter could not be loaded into a [`FilterList`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilterErrorKind {
+    /// The line is not valid UTF-8.
+    InvalidUtf8,
+    /// The filter is not a domain-level filter, e.g. a cosmetic filter or one that matches URL
+    /// paths. Regex filters are also unsupported without the `regex` feature.
+    Unsupported,
+    /// The filter has a `$` modifier other than `important`. The filter is rejected as a whole,
+    /// as loading it without the modifier would block more than intended.
+    UnsupportedModifier(String),
+    /// The domain or regex of the filter is invalid.
+    InvalidPattern(PatternError),
+}
+
+impl fmt::Display for FilterErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterErrorKind::InvalidUtf8 => write!(f, "line is not valid UTF-8"),
+            FilterErrorKind::Unsupported => write!(f, "not a domain-level filter"),
+            FilterErrorKind::UnsupportedModifier(modifier) => {
+                write!(f, "unsupported modifier \"{}\"", modifier)
+            }
+            FilterErrorKind::InvalidPattern(error) => write!(f, "invalid pattern: {}", error),
+        }
+    }
+}
+
+impl Error for FilterErrorKind {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FilterErrorKind::InvalidPattern(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A filter of a list that could not be loaded, see [`FilterList::load`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError {
+    line: usize,
+    filter: String,
+    kind: FilterErrorKind,
+}
+
+impl FilterError {
+    /// Returns the line the filter is on, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the filter as it appears in the list.
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// Returns the reason the filter was rejected.
+    pub fn kind(&self) -> &FilterErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {:?}: {}", self.line, self.filter, self.kind)
+    }
+}
+
+impl Error for FilterError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.kind.source()
+    }
+}
+
+/// Rules of one kind, e.g. the important exceptions, with the filter each came from.
+#[derive(Debug)]
+struct Rules {
+    names: DomainLookupMap<String>,
+    #[cfg(feature = "regex")]
+    regexes: Vec<(regex::Regex, String)>,
+}
+
+impl Rules {
+    fn new(builder: &DomainLookupBuilder) -> Rules {
+        Rules {
+            names: builder.clone().build(),
+            #[cfg(feature = "regex")]
+            regexes: Vec::new(),
+        }
+    }
+
+    /// Returns the filter of a rule matching `domain`, preferring the most specific name rule
+    /// over regexes.
+    fn find(&self, domain: &str) -> Option<&str> {
+        if let Some(filter) = self.names.lookup(domain) {
+            return Some(filter);
+        }
+        #[cfg(feature = "regex")]
+        {
+            let domain = normalize(strip_root(domain));
+            if let Some((_, filter)) = self.regexes.iter().find(|(r, _)| r.is_match(&domain)) {
+                return Some(filter);
+            }
+        }
+
+        None
+    }
+}
+
+/// A filter parsed from a list, borrowing from its line.
+struct Filter<'a> {
+    exception: bool,
+    important: bool,
+    target: Target<'a>,
+}
+
+enum Target<'a> {
+    /// A rule in [`DomainLookupMap`] syntax.
+    Name(String),
+    /// The source of a regex, matched against the whole domain.
+    #[cfg_attr(not(feature = "regex"), allow(dead_code))]
+    Regex(&'a str),
+}
+
+/// The domain-level subset of adblock filter lists, as used by EasyList and AdGuard DNS.
+///
+/// Supported filters are:
+/// - `||example.com^`, which blocks example.com and every name below it
+/// - `@@||good.example.com^`, an exception which allows a name blocked by another filter
+/// - `/^ads[0-9]+\./`, a regex matched against the whole domain, with the `regex` feature
+/// - `$important` after any of the above, which lets a block filter override exceptions that
+///   aren't important themselves
+///
+/// Names may contain `*` wildcards, as in `||ads*.example.com^`. Unlike the `!` exceptions of a
+/// [`DomainLookupMap`], an exception applies whenever it matches, even if a block filter for the
+/// same domain is more specific.
+///
+/// # Examples
+///
+/// ```
+/// use domain_lookup_tree::{FilterList, Verdict};
+///
+/// let mut filters = FilterList::new();
+/// filters.insert("||example.com^").unwrap();
+/// filters.insert("@@||good.example.com^").unwrap();
+///
+/// assert!(filters.is_blocked("ads.example.com"));
+/// assert!(!filters.is_blocked("www.good.example.com"));
+/// assert_eq!(filters.verdict("good.example.com"), Some(Verdict::Excluded("@@||good.example.com^")));
+/// ```
+#[derive(Debug)]
+pub struct FilterList {
+    block: Rules,
+    allow: Rules,
+    important_block: Rules,
+    important_allow: Rules,
+}
+
+impl FilterList {
+    /// Returns a new, empty FilterList.
+    pub fn new() -> FilterList {
+        FilterList::with_builder(DomainLookupBuilder::new())
+    }
+
+    /// Returns a new, empty FilterList which validates the names of its filters with the options
+    /// of `builder`. As every "||" filter is a wildcard, this is where
+    /// [`public_suffixes`](DomainLookupBuilder::public_suffixes) and
+    /// [`minimum_level`](DomainLookupBuilder::minimum_level) keep filters such as "||co.uk^"
+    /// out of the list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainLookupBuilder, FilterErrorKind, FilterList, PatternError};
+    ///
+    /// let mut filters = FilterList::with_builder(DomainLookupBuilder::new().minimum_level(2));
+    /// assert_eq!(filters.insert("||example.com^"), Ok(true));
+    /// assert_eq!(
+    ///     filters.insert("||com^"),
+    ///     Err(FilterErrorKind::InvalidPattern(PatternError::BelowMinimumLevel {
+    ///         labels: 1,
+    ///         minimum: 2
+    ///     }))
+    /// );
+    /// ```
+    pub fn with_builder(builder: DomainLookupBuilder) -> FilterList {
+        FilterList {
+            block: Rules::new(&builder),
+            allow: Rules::new(&builder),
+            important_block: Rules::new(&builder),
+            important_allow: Rules::new(&builder),
+        }
+    }
+
+    /// Adds a filter to the list, returning whether it was newly added. Comments, blank lines
+    /// and "[Adblock Plus 2.0]" style headers are ignored, returning `false`.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filter in adblock syntax, e.g. "||example.com^"
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FilterErrorKind`] if the filter is not supported or invalid.
+    pub fn insert(&mut self, filter: &str) -> Result<bool, FilterErrorKind> {
+        let filter = filter.trim();
+        let parsed = match parse(filter)? {
+            Some(parsed) => parsed,
+            None => return Ok(false),
+        };
+
+        let rules = match (parsed.important, parsed.exception) {
+            (false, false) => &mut self.block,
+            (false, true) => &mut self.allow,
+            (true, false) => &mut self.important_block,
+            (true, true) => &mut self.important_allow,
+        };
+        match parsed.target {
+            Target::Name(rule) => rules
+                .names
+                .insert(&rule, filter.to_owned())
+                .map(|previous| previous.is_none())
+                .map_err(FilterErrorKind::InvalidPattern),
+            #[cfg(feature = "regex")]
+            Target::Regex(source) => {
+                if let Some((_, previous)) =
+                    rules.regexes.iter_mut().find(|(r, _)| r.as_str() == source)
+                {
+                    *previous = filter.to_owned();
+                    return Ok(false);
+                }
+                let regex =
+                    regex_rules::compile(source).map_err(FilterErrorKind::InvalidPattern)?;
+                rules.regexes.push((regex, filter.to_owned()));
+                Ok(true)
+            }
+            #[cfg(not(feature = "regex"))]
+            Target::Regex(_) => Err(FilterErrorKind::Unsupported),
+        }
+    }
+
+    /// Loads every filter of a list, e.g. EasyList or an AdGuard DNS filter, into the
+    /// FilterList.
+    ///
+    /// Filters that can't be loaded don't stop the import; every one of them is reported instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The filter list to read
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from `reader` fails. Otherwise returns a [`FilterError`] for
+    /// every filter that could not be loaded, in the order they appear.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{FilterErrorKind, FilterList};
+    ///
+    /// let list = "! Title: Example\n||ads.example.com^\n||example.net^$third-party\n";
+    /// let mut filters = FilterList::new();
+    /// let errors = filters.load(list.as_bytes()).unwrap();
+    /// assert_eq!(errors[0].line(), 3);
+    /// assert_eq!(
+    ///     errors[0].kind(),
+    ///     &FilterErrorKind::UnsupportedModifier("third-party".to_owned())
+    /// );
+    /// assert!(filters.is_blocked("ads.example.com"));
+    /// assert!(!filters.is_blocked("example.net"));
+    /// ```
+    pub fn load<R: BufRead>(&mut self, reader: R) -> io::Result<Vec<FilterError>> {
+        let mut errors = Vec::new();
+
+        read_lines(reader, |line, filter| {
+            let result = match filter {
+                Ok(filter) => self
+                    .insert(filter)
+                    .map_err(|kind| (filter.to_owned(), kind)),
+                Err(lossy) => Err((lossy.into_owned(), FilterErrorKind::InvalidUtf8)),
+            };
+            if let Err((filter, kind)) = result {
+                errors.push(FilterError {
+                    line,
+                    filter: filter.trim().to_owned(),
+                    kind,
+                });
+            }
+        })?;
+
+        Ok(errors)
+    }
+
+    /// Decides whether `domain` is blocked, returning the filter that decided it:
+    /// [`Verdict::Matched`] with a block filter, [`Verdict::Excluded`] with an exception, or
+    /// `None` if no block filter matches.
+    ///
+    /// An important exception beats every block filter, an important block filter beats the
+    /// other exceptions, and those beat the other block filters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{FilterList, Verdict};
+    ///
+    /// let mut filters = FilterList::new();
+    /// filters.insert("||ads.example.com^$important").unwrap();
+    /// filters.insert("@@||example.com^").unwrap();
+    ///
+    /// assert_eq!(filters.verdict("ads.example.com"), Some(Verdict::Matched("||ads.example.com^$important")));
+    /// assert_eq!(filters.verdict("www.example.com"), None);
+    /// ```
+    pub fn verdict(&self, domain: &str) -> Option<Verdict<&str>> {
+        let block = self.important_block.find(domain);
+        let block = match block {
+            Some(filter) => Some((filter, self.important_allow.find(domain))),
+            None => self.block.find(domain).map(|filter| {
+                let allow = self.important_allow.find(domain);
+                (filter, allow.or_else(|| self.allow.find(domain)))
+            }),
+        };
+
+        match block? {
+            (_, Some(exception)) => Some(Verdict::Excluded(exception)),
+            (filter, None) => Some(Verdict::Matched(filter)),
+        }
+    }
+
+    /// Returns whether `domain` is blocked by a filter and not allowed by an exception.
+    pub fn is_blocked(&self, domain: &str) -> bool {
+        matches!(self.verdict(domain), Some(Verdict::Matched(_)))
+    }
+}
+
+impl Default for FilterList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses a trimmed filter, returning `None` for comments and blank lines.
+fn parse(filter: &str) -> Result<Option<Filter<'_>>, FilterErrorKind> {
+    // "!" starts comments in adblock lists, "#" in AdGuard DNS lists, unless it starts a generic
+    // cosmetic filter such as "##.banner"
+    let cosmetic = COSMETIC_MARKERS
+        .iter()
+        .any(|marker| filter.starts_with(marker));
+    if filter.is_empty() || filter.starts_with('!') || (filter.starts_with('#') && !cosmetic) {
+        return Ok(None);
+    }
+    if filter.starts_with('[') && filter.ends_with(']') {
+        return Ok(None);
+    }
+    if COSMETIC_MARKERS
+        .iter()
+        .any(|marker| filter.contains(marker))
+    {
+        return Err(FilterErrorKind::Unsupported);
+    }
+
+    let (exception, body) = match filter.strip_prefix("@@") {
+        Some(body) => (true, body),
+        None => (false, filter),
+    };
+    let (body, modifiers) = split_modifiers(body);
+
+    let mut important = false;
+    for modifier in modifiers.into_iter().flat_map(|m| m.split(',')) {
+        match modifier.trim() {
+            "important" => important = true,
+            other => return Err(FilterErrorKind::UnsupportedModifier(other.to_owned())),
+        }
+    }
+
+    let target = if body.len() > 2 && body.starts_with('/') && body.ends_with('/') {
+        Target::Regex(&body[1..body.len() - 1])
+    } else if let Some(name) = body.strip_prefix("||") {
+        let name = name.strip_suffix('|').unwrap_or(name);
+        let name = name.strip_suffix('^').ok_or(FilterErrorKind::Unsupported)?;
+        if name.contains(['/', ':', '^', '|', '$', '{', '}']) {
+            return Err(FilterErrorKind::Unsupported);
+        }
+        Target::Name(format!(".{}", name))
+    } else {
+        return Err(FilterErrorKind::Unsupported);
+    };
+
+    Ok(Some(Filter {
+        exception,
+        important,
+        target,
+    }))
+}
+
+/// Splits the "$" modifiers off a filter. For regex filters, only a "$" after the closing slash
+/// starts the modifiers, as the regex may contain one itself.
+fn split_modifiers(body: &str) -> (&str, Option<&str>) {
+    if body.starts_with('/') {
+        if let Some(end) = body.rfind('/').filter(|&end| end > 0) {
+            if let Some(modifiers) = body[end + 1..].strip_prefix('$') {
+                return (&body[..=end], Some(modifiers));
+            }
+            if end + 1 == body.len() {
+                return (body, None);
+            }
+        }
+    }
+
+    match body.find('$') {
+        Some(dollar) => (&body[..dollar], Some(&body[dollar + 1..])),
+        None => (body, None),
+    }
+}
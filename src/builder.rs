@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use crate::{DomainLookupMap, DomainLookupTree, Profile, PublicSuffixList};
+
+/// Configures how a [`DomainLookupMap`] or [`DomainLookupTree`] validates the rules inserted
+/// into it, for options beyond the [`Profile`] given to `with_profile`.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use domain_lookup_tree::{DomainLookupBuilder, PatternError, Profile, PublicSuffixList};
+///
+/// let list = Arc::new(PublicSuffixList::parse("uk\nco.uk").unwrap());
+/// let mut tree = DomainLookupBuilder::new()
+///     .profile(Profile::Hostname)
+///     .public_suffixes(list)
+///     .build_tree();
+///
+/// assert!(tree.insert(".example.co.uk").is_ok());
+/// assert_eq!(
+///     tree.insert(".co.uk"),
+///     Err(PatternError::PublicSuffixWildcard("co.uk".to_owned()))
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DomainLookupBuilder {
+    profile: Profile,
+    minimum_level: usize,
+    public_suffixes: Option<Arc<PublicSuffixList>>,
+}
+
+impl DomainLookupBuilder {
+    /// Returns a new builder with the default options.
+    pub fn new() -> DomainLookupBuilder {
+        DomainLookupBuilder::default()
+    }
+
+    /// Sets the [`Profile`] inserted rules are validated with.
+    pub fn profile(mut self, profile: Profile) -> DomainLookupBuilder {
+        self.profile = profile;
+        self
+    }
+
+    /// Rejects rules whose name has fewer than `labels` labels with
+    /// [`PatternError::BelowMinimumLevel`](crate::PatternError::BelowMinimumLevel), e.g. "com"
+    /// and ".com" with a minimum level of 2. Wildcard prefixes and depth limits don't count
+    /// towards the level, so "*.com" is rejected as well. Use `try_lookup` on the map or tree
+    /// to also refuse lookups of names shorter than that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainLookupBuilder, PatternError};
+    ///
+    /// let mut tree = DomainLookupBuilder::new().minimum_level(2).build_tree();
+    /// assert_eq!(tree.insert(".example.com"), Ok(true));
+    /// assert_eq!(
+    ///     tree.insert(".com"),
+    ///     Err(PatternError::BelowMinimumLevel { labels: 1, minimum: 2 })
+    /// );
+    /// ```
+    pub fn minimum_level(mut self, labels: usize) -> DomainLookupBuilder {
+        self.minimum_level = labels;
+        self
+    }
+
+    /// Rejects wildcard rules whose name is a public suffix according to `list`, such as
+    /// ".co.uk" or "*.github.io", and regex rules attached to a public suffix, with
+    /// [`PatternError::PublicSuffixWildcard`](crate::PatternError::PublicSuffixWildcard). See
+    /// [`PublicSuffixList::check`] for which rules are affected.
+    pub fn public_suffixes(mut self, list: Arc<PublicSuffixList>) -> DomainLookupBuilder {
+        self.public_suffixes = Some(list);
+        self
+    }
+
+    /// Returns a new, empty DomainLookupMap with the configured options.
+    pub fn build<V>(self) -> DomainLookupMap<V> {
+        DomainLookupMap {
+            nodes: Default::default(),
+            exceptions: Default::default(),
+            minimum_level: self.minimum_level,
+            profile: self.profile,
+            public_suffixes: self.public_suffixes,
+        }
+    }
+
+    /// Returns a new, empty DomainLookupTree with the configured options.
+    pub fn build_tree(self) -> DomainLookupTree {
+        DomainLookupTree { map: self.build() }
+    }
+}
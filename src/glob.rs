@@ -0,0 +1,49 @@
+use std::cmp::Reverse;
+
+use crate::WILDCARD_LABEL;
+
+/// Returns whether `label` is a glob such as "cdn-*" or "node??", as opposed to a literal label
+/// or the plain wildcard label, which are looked up by key.
+pub(crate) fn is_glob(label: &str) -> bool {
+    label != WILDCARD_LABEL && label.contains(['*', '?'])
+}
+
+/// The order globs on the same level are tried in: those with more literal characters first, as
+/// they are more specific, then alphabetically.
+pub(crate) fn order(glob: &str) -> (Reverse<usize>, &str) {
+    let literal = glob.bytes().filter(|&b| b != b'*' && b != b'?').count();
+    (Reverse(literal), glob)
+}
+
+/// Returns whether `label` matches `glob`, where "*" matches any run of characters, including
+/// none, and "?" matches any single character.
+pub(crate) fn matches(glob: &str, label: &str) -> bool {
+    let (glob, label) = (glob.as_bytes(), label.as_bytes());
+    let (mut g, mut l) = (0, 0);
+    // The position of the last "*" seen and how much of the label it has consumed
+    let mut backtrack = None;
+
+    while l < label.len() {
+        match glob.get(g) {
+            Some(b'*') => {
+                backtrack = Some((g, l));
+                g += 1;
+            }
+            Some(&c) if c == b'?' || c == label[l] => {
+                g += 1;
+                l += 1;
+            }
+            _ => match backtrack {
+                // Let the last "*" consume one more character and try again from there
+                Some((star, consumed)) => {
+                    backtrack = Some((star, consumed + 1));
+                    g = star + 1;
+                    l = consumed + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|&c| c == b'*')
+}
@@ -0,0 +1,173 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::net::{IpAddr, Ipv6Addr};
+
+use crate::lines::read_lines;
+use crate::PatternError;
+
+/// Names which hosts files map to the local machine or to special addresses, rather than block.
+const LOCAL_NAMES: &[&str] = &[
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "ip6-localnet",
+    "ip6-mcastprefix",
+    "ip6-allnodes",
+    "ip6-allrouters",
+    "ip6-allhosts",
+    "0.0.0.0",
+];
+
+/// Why an entry of a hosts file was not loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HostsErrorKind {
+    /// The line is not valid UTF-8.
+    InvalidUtf8,
+    /// The line does not start with an IP address.
+    InvalidAddress,
+    /// The line has an address but no names.
+    MissingHostname,
+    /// A name could not be inserted, either because it is not a valid plain name or because the
+    /// tree rejected it.
+    InvalidHostname(PatternError),
+}
+
+impl fmt::Display for HostsErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostsErrorKind::InvalidUtf8 => write!(f, "line is not valid UTF-8"),
+            HostsErrorKind::InvalidAddress => write!(f, "invalid address"),
+            HostsErrorKind::MissingHostname => write!(f, "address has no hostnames"),
+            HostsErrorKind::InvalidHostname(error) => write!(f, "invalid hostname: {}", error),
+        }
+    }
+}
+
+/// An entry of a hosts file that could not be loaded, see
+/// [`DomainLookupTree::load_hosts`](crate::DomainLookupTree::load_hosts).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostsError {
+    line: usize,
+    entry: String,
+    kind: HostsErrorKind,
+}
+
+impl HostsError {
+    /// Returns the line the entry is on, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the part of the line that was rejected: the address, a single hostname, or the
+    /// whole line if no part of it is to blame.
+    pub fn entry(&self) -> &str {
+        &self.entry
+    }
+
+    /// Returns the reason the entry was rejected.
+    pub fn kind(&self) -> &HostsErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for HostsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {:?}: {}", self.line, self.entry, self.kind)
+    }
+}
+
+impl Error for HostsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            HostsErrorKind::InvalidHostname(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a hosts file line by line, passing every name in it to `insert` and collecting a
+/// [`HostsError`] for each entry that is malformed or rejected.
+pub(crate) fn load<R, F>(reader: R, mut insert: F) -> io::Result<Vec<HostsError>>
+where
+    R: BufRead,
+    F: FnMut(&str) -> Result<(), PatternError>,
+{
+    let mut errors = Vec::new();
+
+    read_lines(reader, |line_number, line| {
+        let line = match line {
+            Ok(line) => line,
+            Err(lossy) => {
+                errors.push(HostsError {
+                    line: line_number,
+                    entry: lossy.trim().to_owned(),
+                    kind: HostsErrorKind::InvalidUtf8,
+                });
+                return;
+            }
+        };
+        // Everything after a "#" is a comment
+        let line = line.split('#').next().unwrap_or_default();
+
+        let mut fields = line.split_whitespace();
+        let address = match fields.next() {
+            Some(address) => address,
+            None => return,
+        };
+        let mut error = |entry: &str, kind| {
+            errors.push(HostsError {
+                line: line_number,
+                entry: entry.to_owned(),
+                kind,
+            })
+        };
+        // Link-local IPv6 addresses may name the interface they are scoped to, as in "fe80::1%lo0"
+        let valid = match address.split_once('%') {
+            Some((ip, zone)) => !zone.is_empty() && ip.parse::<Ipv6Addr>().is_ok(),
+            None => address.parse::<IpAddr>().is_ok(),
+        };
+        if !valid {
+            error(address, HostsErrorKind::InvalidAddress);
+            return;
+        }
+
+        let mut names = fields.peekable();
+        if names.peek().is_none() {
+            error(line.trim(), HostsErrorKind::MissingHostname);
+        }
+        for name in names {
+            if LOCAL_NAMES
+                .iter()
+                .any(|local| local.eq_ignore_ascii_case(name))
+            {
+                continue;
+            }
+            if let Err(e) = plain_name(name).and_then(|_| insert(name)) {
+                error(name, HostsErrorKind::InvalidHostname(e));
+            }
+        }
+    })?;
+
+    Ok(errors)
+}
+
+/// Rejects names which would be read as something other than an exact rule, as hosts files
+/// have no wildcards or exceptions.
+fn plain_name(name: &str) -> Result<(), PatternError> {
+    if let Some(c) = name
+        .chars()
+        .find(|c| matches!(c, '*' | '?' | '!' | '+' | '{' | '}'))
+    {
+        return Err(PatternError::InvalidCharacter(c));
+    }
+    if name.starts_with('.') {
+        return Err(PatternError::EmptyLabel);
+    }
+
+    Ok(())
+}
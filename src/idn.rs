@@ -0,0 +1,55 @@
+use std::borrow::Cow;
+
+use idna::AsciiDenyList;
+
+/// Converts an internationalized `domain` to A-labels, applying the UTS-46 mapping and
+/// normalization. ASCII letters are lowercased along the way.
+pub(crate) fn to_ascii(domain: &str) -> Result<Cow<'_, str>, idna::Errors> {
+    idna::domain_to_ascii_cow(domain.as_bytes(), AsciiDenyList::EMPTY)
+}
+
+/// Converts a rule, or any other name, from A-labels to U-labels for display. Exception and
+/// wildcard prefixes, depth limits and the regex of regex rules are kept as they are. Labels that
+/// are not valid A-labels are left untouched.
+///
+/// Rules containing internationalized names are stored, iterated over and matched in their A-label
+/// form, so this can be used on the output of iterators and [`Match::rule`](crate::Match::rule).
+///
+/// # Examples
+///
+/// ```
+/// use domain_lookup_tree::{to_unicode, DomainLookupTree};
+///
+/// let mut tree = DomainLookupTree::new();
+/// tree.insert(".bücher.example").unwrap();
+///
+/// let rule = tree.lookup("www.xn--bcher-kva.example").unwrap();
+/// assert_eq!(rule, ".xn--bcher-kva.example");
+/// assert_eq!(to_unicode(&rule), ".bücher.example");
+/// assert_eq!(to_unicode("!+.xn--bcher-kva.example{1,2}"), "!+.bücher.example{1,2}");
+/// ```
+pub fn to_unicode(rule: &str) -> String {
+    let mut start = rule.len() - rule.trim_start_matches(['!', '.', '+']).len();
+    // A regex rule is only followed by its zone, e.g. "/^ads/.xn--bcher-kva.example"
+    if rule[start..].starts_with('/') {
+        if let Some(slash) = rule.rfind("/.") {
+            start = slash + 2;
+        }
+    }
+    let mut end = rule.len();
+    if rule.ends_with('}') {
+        if let Some(brace) = rule[start..].rfind('{') {
+            end = start + brace;
+        }
+    }
+
+    let labels = rule[start..end]
+        .split('.')
+        .map(|label| match idna::domain_to_unicode(label) {
+            (unicode, Ok(())) if label.to_ascii_lowercase().starts_with("xn--") => unicode,
+            _ => label.to_owned(),
+        })
+        .collect::<Vec<_>>();
+
+    format!("{}{}{}", &rule[..start], labels.join("."), &rule[end..])
+}
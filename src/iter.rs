@@ -0,0 +1,319 @@
+use std::collections::hash_map;
+
+use crate::{rule_name, DomainLookupMap, DomainLookupTree, Node, RuleKind};
+
+/// An iterator over the rules of a [`DomainLookupMap`] and references to their values.
+///
+/// This `struct` is created by [`DomainLookupMap::iter`].
+pub struct Iter<'a, V> {
+    start: Vec<(bool, &'a Node<V>)>,
+    stack: Vec<(bool, hash_map::Values<'a, String, Node<V>>)>,
+    pending: Vec<(String, &'a V)>,
+}
+
+impl<'a, V> Iter<'a, V> {
+    pub(crate) fn new(map: &'a DomainLookupMap<V>) -> Self {
+        Self {
+            start: Vec::new(),
+            stack: vec![(true, map.exceptions.values()), (false, map.nodes.values())],
+            pending: Vec::new(),
+        }
+    }
+
+    /// Creates an iterator over the rules stored on the given nodes and all of their
+    /// descendants, where each node comes with whether it belongs to the exception rules.
+    pub(crate) fn under(start: Vec<(bool, &'a Node<V>)>) -> Self {
+        Self {
+            start,
+            stack: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (String, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(rule) = self.pending.pop() {
+                return Some(rule);
+            }
+
+            let (exception, node) = match self.start.pop() {
+                Some(start) => start,
+                None => {
+                    let (exception, children) = self.stack.last_mut()?;
+                    match children.next() {
+                        Some(node) => (*exception, node),
+                        None => {
+                            self.stack.pop();
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            // Rules are popped off the end, so queue them in reverse
+            for (limit, name, value) in node.bounded.iter().rev() {
+                let rule = rule_name(name, RuleKind::Wildcard, Some(*limit), exception);
+                self.pending.push((rule, value));
+            }
+            #[cfg(feature = "regex")]
+            for (regex, name, value) in node.regexes.iter().rev() {
+                let rule = crate::regex_rules::rule_name(name, regex, exception);
+                self.pending.push((rule, value));
+            }
+            for (kind, rule) in RuleKind::ALL.iter().zip(&node.rules).rev() {
+                if let Some((name, value)) = rule {
+                    let rule = rule_name(name, *kind, None, exception);
+                    self.pending.push((rule, value));
+                }
+            }
+            self.stack.push((exception, node.nodes.values()));
+        }
+    }
+}
+
+/// A mutable iterator over the rules of a [`DomainLookupMap`] and their values.
+///
+/// This `struct` is created by [`DomainLookupMap::iter_mut`].
+pub struct IterMut<'a, V> {
+    stack: Vec<(bool, hash_map::ValuesMut<'a, String, Node<V>>)>,
+    pending: Vec<(String, &'a mut V)>,
+}
+
+impl<'a, V> IterMut<'a, V> {
+    pub(crate) fn new(map: &'a mut DomainLookupMap<V>) -> Self {
+        Self {
+            stack: vec![
+                (true, map.exceptions.values_mut()),
+                (false, map.nodes.values_mut()),
+            ],
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<'a, V> Iterator for IterMut<'a, V> {
+    type Item = (String, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(rule) = self.pending.pop() {
+                return Some(rule);
+            }
+
+            let (exception, children) = self.stack.last_mut()?;
+            let exception = *exception;
+            let Node {
+                rules,
+                bounded,
+                #[cfg(feature = "regex")]
+                regexes,
+                nodes,
+                ..
+            } = match children.next() {
+                Some(node) => node,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            // Rules are popped off the end, so queue them in reverse
+            for (limit, name, value) in bounded.iter_mut().rev() {
+                let rule = rule_name(name, RuleKind::Wildcard, Some(*limit), exception);
+                self.pending.push((rule, value));
+            }
+            #[cfg(feature = "regex")]
+            for (regex, name, value) in regexes.iter_mut().rev() {
+                let rule = crate::regex_rules::rule_name(name, regex, exception);
+                self.pending.push((rule, value));
+            }
+            for (kind, rule) in RuleKind::ALL.iter().zip(rules).rev() {
+                if let Some((name, value)) = rule {
+                    let rule = rule_name(name, *kind, None, exception);
+                    self.pending.push((rule, value));
+                }
+            }
+            self.stack.push((exception, nodes.values_mut()));
+        }
+    }
+}
+
+/// An owning iterator over the rules of a [`DomainLookupMap`] and their values.
+///
+/// This `struct` is created by the `into_iter` method on [`DomainLookupMap`].
+pub struct IntoIter<V> {
+    stack: Vec<(bool, hash_map::IntoValues<String, Node<V>>)>,
+    pending: Vec<(String, V)>,
+}
+
+impl<V> IntoIter<V> {
+    pub(crate) fn new(map: DomainLookupMap<V>) -> Self {
+        Self {
+            stack: vec![
+                (true, map.exceptions.into_values()),
+                (false, map.nodes.into_values()),
+            ],
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<V> Iterator for IntoIter<V> {
+    type Item = (String, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(rule) = self.pending.pop() {
+                return Some(rule);
+            }
+
+            let (exception, children) = self.stack.last_mut()?;
+            let exception = *exception;
+            let Node {
+                rules,
+                bounded,
+                #[cfg(feature = "regex")]
+                regexes,
+                nodes,
+                ..
+            } = match children.next() {
+                Some(node) => node,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            // Rules are popped off the end, so queue them in reverse
+            for (limit, name, value) in bounded.into_iter().rev() {
+                let rule = rule_name(&name, RuleKind::Wildcard, Some(limit), exception);
+                self.pending.push((rule, value));
+            }
+            #[cfg(feature = "regex")]
+            for (regex, name, value) in regexes.into_iter().rev() {
+                let rule = crate::regex_rules::rule_name(&name, &regex, exception);
+                self.pending.push((rule, value));
+            }
+            for (kind, rule) in RuleKind::ALL
+                .iter()
+                .zip(IntoIterator::into_iter(rules))
+                .rev()
+            {
+                if let Some((name, value)) = rule {
+                    let rule = rule_name(&name, *kind, None, exception);
+                    self.pending.push((rule, value));
+                }
+            }
+            self.stack.push((exception, nodes.into_values()));
+        }
+    }
+}
+
+/// An iterator over the rules of a [`DomainLookupMap`] or [`DomainLookupTree`].
+///
+/// This `struct` is created by [`DomainLookupMap::keys`], [`DomainLookupTree::iter`] and
+/// [`DomainLookupTree::rules_under`].
+pub struct Keys<'a, V> {
+    inner: Iter<'a, V>,
+}
+
+impl<'a, V> Keys<'a, V> {
+    pub(crate) fn new(inner: Iter<'a, V>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, V> Iterator for Keys<'a, V> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(rule, _)| rule)
+    }
+}
+
+/// An iterator over the values of a [`DomainLookupMap`].
+///
+/// This `struct` is created by [`DomainLookupMap::values`].
+pub struct Values<'a, V> {
+    inner: Iter<'a, V>,
+}
+
+impl<'a, V> Values<'a, V> {
+    pub(crate) fn new(inner: Iter<'a, V>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, V> Iterator for Values<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+}
+
+/// An owning iterator over the rules of a [`DomainLookupTree`].
+///
+/// This `struct` is created by the `into_iter` method on [`DomainLookupTree`].
+pub struct IntoKeys {
+    inner: IntoIter<()>,
+}
+
+impl Iterator for IntoKeys {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(rule, _)| rule)
+    }
+}
+
+impl<'a, V> IntoIterator for &'a DomainLookupMap<V> {
+    type Item = (String, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a mut DomainLookupMap<V> {
+    type Item = (String, &'a mut V);
+    type IntoIter = IterMut<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<V> IntoIterator for DomainLookupMap<V> {
+    type Item = (String, V);
+    type IntoIter = IntoIter<V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self)
+    }
+}
+
+impl<'a> IntoIterator for &'a DomainLookupTree {
+    type Item = String;
+    type IntoIter = Keys<'a, ()>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for DomainLookupTree {
+    type Item = String;
+    type IntoIter = IntoKeys;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoKeys {
+            inner: self.map.into_iter(),
+        }
+    }
+}
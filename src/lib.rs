@@ -1,70 +1,397 @@
-use std::collections::HashMap;
+//! DomainLookupTree is a data structure which provides efficient domain name lookup matching with
+//! support for wildcard entries.
+//!
+//! Requirements for this implementation:
+//! - Given a domain name, determine if it matches an entry in the tree
+//! - There can be an ever-growing amount of tree entries
+//! - Entries can be absolute matches, e.g.: www.google.com
+//! - Entries may be wildcard entries, which is denoted in the entry by providing a leading dot,
+//!   e.g.: .twitter.com, .en.wikipedia.org, .giggl.app
+//! - Wildcard entries may limit how many labels below the name they match with a suffix, e.g.:
+//!   .example.com{1,2}
+//! - Entries may be subdomain wildcard entries, which is denoted by a leading "+." and matches
+//!   every descendant of the name but not the name itself, e.g.: +.cdn.example.com
+//! - Entries may be single-label wildcard entries, which is denoted by a leading "*." and matches
+//!   exactly one label below the name but not the name itself, e.g.: *.example.com
+//! - Entries may contain wildcard labels, which match exactly one arbitrary label at that position,
+//!   e.g.: api.*.example.com, *.cdn.*.example.net
+//! - Entries may contain glob labels, in which "*" matches any run of characters and "?" any
+//!   single character, e.g.: cdn-*.example.com, node??.example.net
+//! - With the `regex` feature, regular expressions may be attached to a name, which are matched
+//!   against the part of a domain below that name, e.g.: ^[a-z0-9]{16}$ under evil.net
+//! - Entries may end in a wildcard label, which matches any suffix of one or more labels, e.g.:
+//!   example.*, .example.*
+//! - Entries may be exceptions, which is denoted by a leading "!" and keeps the names they match
+//!   from matching any less specific entry, e.g.: !good.ads.example.com next to .ads.example.com
+//!
+//! To achieve this, we implement a simple tree-style structure which has a root structure that
+//! contains a HashMap of nodes. These nodes can then contain other node decendants, and also be
+//! marked as "wildcard" which means theres a rule that matches that domain level and all of its
+//! decendants.
+//!
+//! If, when performing a lookup, the search domain contains segments deeper than the wildcard
+//! match, it can continue to traverse the tree until it exhausts its lookup options. At that
+//! point, the deepest wildcard entry found would be returned, if no absolute match was found.
+//!
+//! It's good to keep in mind that, when traversing the tree, domain names are sorted by top level
+//! to infinite n-level, or in simpler terms, in reverse. This means that if "google.com" is looked
+//! up in the tree, it would split by ".", reverse the vector, then first perform a root node
+//! lookup for "com", and so on.
+//!
+//! Walking down the tree - the story of a lookup:
+//! Let's say have a DomainLookupTree with an entry ".giggl.app" which means that the tree looks
+//! like this:
+//!
+//! ```text
+//! app
+//! └── giggl [wildcard]
+//! ```
+//!
+//! A domain lookup for "canary.giggl.app" is requested. First, "app" is matched, but it's not a
+//! wildcard, so it's ignored. We now check the decendants of "app" for "giggl" - it matches, and
+//! it's a wildcard match, so we store it within the context of the lookup. This lookup will now
+//! 100% return a match, even if it isn't absolute. Anyway, we now check the decendants of "giggl"
+//! for "canary", though it doesn't exist, and the traversal ends. Now, we didn't have an absolute
+//! match, but we did have a wildcard match earlier on for ".giggl.app", so we successfully return
+//! the result ".giggl.app" from the lookup function.
+//!
+//! The same structure also backs [`PublicSuffixList`], which answers which part of a domain is its
+//! public suffix and which is its registrable domain, and [`FilterList`], which loads adblock
+//! style filter lists.
+//!
+//! The tree comes in two flavours: [`DomainLookupTree`] stores a set of rules and reports which
+//! rule matched, while [`DomainLookupMap`] attaches an arbitrary value to every rule and returns
+//! the value of the most specific match.
 
-/// DomainLookupTree is a data structure which provides efficient domain name lookup matching with
-/// support for wildcard entries.
-///
-/// Requirements for this implementation:
-/// - Given a domain name, determine if it matches an entry in the tree
-/// - There can be an ever-growing amount of tree entries
-/// - Entries can be absolute matches, e.g.: www.google.com
-/// - Entries may be wildcard entries, which is denoted in the entry by providing a leading dot,
-///   e.g.: .twitter.com, .en.wikipedia.org, .giggl.app
-/// - Wildcard entries can not be embedded
-///
-/// To achieve this, we implement a simple tree-style structure which has a root structure that
-/// contains a HashMap of nodes. These nodes can then contain other node decendants, and also be
-/// marked as "wildcard" which means theres a rule that matches that domain level and all of its
-/// decendants.
-///
-/// If, when performing a lookup, the search domain contains segments deeper than the wildcard
-/// match, it can continue to traverse the tree until it exhausts its lookup options. At that
-/// point, the deepest wildcard entry found would be returned, if no absolute match was found.
-///
-/// It's good to keep in mind that, when traversing the tree, domain names are sorted by top level
-/// to infinite n-level, or in simpler terms, in reverse. This means that if "google.com" is looked
-/// up in the tree, it would split by ".", reverse the vector, then first perform a root node
-/// lookup for "com", and so on.
-///
-/// Walking down the tree - the story of a lookup:
-/// Let's say have a DomainLookupTree with an entry ".giggl.app" which means that the tree looks
-/// like this:
-///
-/// app
-/// └── giggl [wildcard]
-///
-/// A domain lookup for "canary.giggl.app" is requested. First, "app" is matched, but it's not a
-/// wildcard, so it's ignored. We now check the decendants of "app" for "giggl" - it matches, and
-/// it's a wildcard match, so we store it within the context of the lookup. This lookup will now
-/// 100% return a match, even if it isn't absolute. Anyway, we now check the decendants of "giggl"
-/// for "canary", though it doesn't exist, and the traversal ends. Now, we didn't have an absolute
-/// match, but we did have a wildcard match earlier on for ".giggl.app", so we successfully return
-/// the result ".giggl.app" from the lookup function.
-///
-///
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{hash_map, HashMap};
+use std::io::{self, BufRead};
+use std::mem;
+use std::sync::Arc;
 
-type NodeList = HashMap<String, Node>;
+mod adblock;
+mod builder;
+mod glob;
+mod hosts;
+#[cfg(feature = "idna")]
+mod idn;
+mod iter;
+mod lines;
+mod matches;
+mod pattern;
+mod psl;
+#[cfg(feature = "regex")]
+mod regex_rules;
 
-#[derive(Debug)]
+pub use adblock::{FilterError, FilterErrorKind, FilterList};
+pub use builder::DomainLookupBuilder;
+pub use hosts::{HostsError, HostsErrorKind};
+#[cfg(feature = "idna")]
+pub use idn::to_unicode;
+pub use iter::{IntoIter, IntoKeys, Iter, IterMut, Keys, Values};
+pub use matches::{Match, Verdict};
+pub use pattern::{DepthLimit, DomainPattern, PatternError, Profile};
+pub use psl::{PslError, PublicSuffixList, Section};
+
+/// A label which matches any single label, e.g. in "api.*.example.com", or any suffix when it is
+/// the rightmost label, e.g. in "example.*". Nodes for it are stored alongside the literal
+/// children of a node, under this key.
+const WILDCARD_LABEL: &str = "*";
+
+/// A set of domain rules. This is a thin wrapper around a [`DomainLookupMap`] with `()` values,
+/// for when you only need to know which rule a domain matched.
+#[derive(Debug, Default)]
 pub struct DomainLookupTree {
-    nodes: NodeList,
+    map: DomainLookupMap<()>,
+}
+
+/// A map from domain rules to values of type `V`. Rules use the same syntax as
+/// [`DomainLookupTree`], and lookups return the value of the most specific matching rule.
+#[derive(Debug)]
+pub struct DomainLookupMap<V> {
+    nodes: NodeList<V>,
+    /// Exception rules live in a tree of their own, shaped like the one for regular rules.
+    exceptions: NodeList<V>,
+    /// Rules with fewer labels than this are rejected.
     minimum_level: usize,
+    profile: Profile,
+    /// When set, wildcard rules on a public suffix of this list are rejected.
+    public_suffixes: Option<Arc<PublicSuffixList>>,
 }
 
+/// The kinds of rule a node can carry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RuleKind {
+    /// Matches only the rule's own name, e.g. "google.com".
+    Exact,
+    /// Matches the rule's name and all of its descendants, e.g. ".google.com".
+    Wildcard,
+    /// Matches names exactly one label below the rule's name, but not the name itself, e.g.
+    /// "*.google.com".
+    SingleLabel,
+    /// Matches all descendants of the rule's name, but not the name itself, e.g. "+.google.com".
+    Subdomains,
+    /// Matches descendants of the rule's name whose labels below it match a regular expression.
+    /// Only available with the `regex` feature.
+    #[cfg(feature = "regex")]
+    Regex,
+}
+
+impl RuleKind {
+    /// Every kind of rule stored in a node's slots, in the order they are stored in.
+    pub(crate) const ALL: [RuleKind; 4] = [
+        RuleKind::Exact,
+        RuleKind::Wildcard,
+        RuleKind::SingleLabel,
+        RuleKind::Subdomains,
+    ];
+}
+
+/// A single label in the tree. A node only carries a rule if one was inserted for its exact name;
+/// nodes created on the way to deeper rules are plain path nodes and never match on their own.
 #[derive(Debug)]
-pub struct Node {
-    wildcard: bool,
-    nodes: NodeList,
+pub struct Node<V> {
+    /// The rules for this node's name, indexed by [`RuleKind`]. Every rule keeps the name it was
+    /// first inserted with, so that it is reported with its own casing.
+    rules: [Option<(String, V)>; 4],
+    /// Wildcard rules for this node's name with a [`DepthLimit`], in insertion order.
+    bounded: Vec<(DepthLimit, String, V)>,
+    /// Regex rules attached to this node's name, in insertion order.
+    #[cfg(feature = "regex")]
+    regexes: Vec<(regex::Regex, String, V)>,
+    nodes: NodeList<V>,
     data: String,
 }
 
-impl Node {
-    fn new(wildcard: bool, data: &str) -> Self {
+/// The children of a node, keyed by their lowercased label. Children for glob labels such as
+/// "cdn-*" are stored alongside the others, and their keys are also kept in the order they are
+/// tried in, so lookups can find them without scanning every child.
+#[derive(Debug)]
+struct NodeList<V> {
+    nodes: HashMap<String, Node<V>>,
+    globs: Vec<String>,
+}
+
+impl<V> NodeList<V> {
+    fn get(&self, key: &str) -> Option<&Node<V>> {
+        self.nodes.get(key)
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut Node<V>> {
+        self.nodes.get_mut(key)
+    }
+
+    /// Returns the child for `label`, creating it if needed. The child remembers the label as it
+    /// was first inserted.
+    fn get_or_insert(&mut self, label: &str) -> &mut Node<V> {
+        let key = label.to_ascii_lowercase();
+        if glob::is_glob(&key) && !self.nodes.contains_key(&key) {
+            let index = self
+                .globs
+                .binary_search_by(|probe| glob::order(probe).cmp(&glob::order(&key)))
+                .unwrap_or_else(|index| index);
+            self.globs.insert(index, key.clone());
+        }
+
+        self.nodes.entry(key).or_insert_with(|| Node::new(label))
+    }
+
+    fn remove(&mut self, key: &str) {
+        if self.nodes.remove(key).is_some() && glob::is_glob(key) {
+            self.globs.retain(|glob| glob != key);
+        }
+    }
+
+    /// Returns the glob children matching `label`, in the order they are to be tried.
+    fn globs_matching<'a: 'l, 'l>(
+        &'a self,
+        label: &'l str,
+    ) -> impl Iterator<Item = &'a Node<V>> + 'l {
+        self.globs
+            .iter()
+            .filter(move |glob| glob::matches(glob, label))
+            .filter_map(move |glob| self.nodes.get(glob))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn values(&self) -> hash_map::Values<'_, String, Node<V>> {
+        self.nodes.values()
+    }
+
+    fn values_mut(&mut self) -> hash_map::ValuesMut<'_, String, Node<V>> {
+        self.nodes.values_mut()
+    }
+
+    fn into_values(self) -> hash_map::IntoValues<String, Node<V>> {
+        self.nodes.into_values()
+    }
+}
+
+impl<V> Default for NodeList<V> {
+    fn default() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            globs: Vec::new(),
+        }
+    }
+}
+
+impl<V> Node<V> {
+    fn new(data: &str) -> Self {
         Self {
-            wildcard,
+            rules: [None, None, None, None],
+            bounded: Vec::new(),
+            #[cfg(feature = "regex")]
+            regexes: Vec::new(),
             nodes: Default::default(),
             data: data.to_owned(),
         }
     }
+
+    /// Returns the rule of the given kind along with the name it was inserted with.
+    fn entry(&self, kind: RuleKind) -> Option<(&str, &V)> {
+        self.rules[kind as usize]
+            .as_ref()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    fn rule(&self, kind: RuleKind) -> Option<&V> {
+        self.entry(kind).map(|(_, value)| value)
+    }
+
+    /// Stores `value` as the rule of the given kind and limit, returning the previous value. Only
+    /// wildcard rules carry a limit. A rule that is already present keeps its name.
+    fn insert_rule(
+        &mut self,
+        kind: RuleKind,
+        limit: Option<DepthLimit>,
+        name: &str,
+        value: V,
+    ) -> Option<V> {
+        let limit = match limit {
+            Some(limit) => limit,
+            None => {
+                return match &mut self.rules[kind as usize] {
+                    Some((_, previous)) => Some(mem::replace(previous, value)),
+                    slot => {
+                        *slot = Some((name.to_owned(), value));
+                        None
+                    }
+                }
+            }
+        };
+
+        match self
+            .bounded
+            .iter_mut()
+            .find(|(bounds, _, _)| *bounds == limit)
+        {
+            Some((_, _, previous)) => Some(mem::replace(previous, value)),
+            None => {
+                self.bounded.push((limit, name.to_owned(), value));
+                None
+            }
+        }
+    }
+
+    fn remove_rule(&mut self, kind: RuleKind, limit: Option<DepthLimit>) -> Option<V> {
+        let limit = match limit {
+            Some(limit) => limit,
+            None => return self.rules[kind as usize].take().map(|(_, value)| value),
+        };
+
+        let index = self
+            .bounded
+            .iter()
+            .position(|(bounds, _, _)| *bounds == limit)?;
+        Some(self.bounded.remove(index).2)
+    }
+
+    /// Stores `value` as the rule for `regex`, returning the previous value. Regexes are told
+    /// apart by their source.
+    #[cfg(feature = "regex")]
+    fn insert_regex(&mut self, regex: regex::Regex, name: &str, value: V) -> Option<V> {
+        match self
+            .regexes
+            .iter_mut()
+            .find(|(existing, _, _)| existing.as_str() == regex.as_str())
+        {
+            Some((_, _, previous)) => Some(mem::replace(previous, value)),
+            None => {
+                self.regexes.push((regex, name.to_owned(), value));
+                None
+            }
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    fn remove_regex(&mut self, regex: &str) -> Option<V> {
+        let index = self
+            .regexes
+            .iter()
+            .position(|(existing, _, _)| existing.as_str() == regex)?;
+        Some(self.regexes.remove(index).2)
+    }
+
+    /// A node that carries no rule and has no descendants serves no purpose in the tree.
+    fn is_prunable(&self) -> bool {
+        #[cfg(feature = "regex")]
+        {
+            if !self.regexes.is_empty() {
+                return false;
+            }
+        }
+
+        self.rules.iter().all(Option::is_none) && self.bounded.is_empty() && self.nodes.is_empty()
+    }
+
+    /// Returns the label this node represents.
+    pub fn label(&self) -> &str {
+        &self.data
+    }
+
+    /// Returns the value attached to the exact rule for this node's name, if any.
+    pub fn exact(&self) -> Option<&V> {
+        self.rule(RuleKind::Exact)
+    }
+
+    /// Returns the value attached to the wildcard rule for this node's name, if any.
+    pub fn wildcard(&self) -> Option<&V> {
+        self.rule(RuleKind::Wildcard)
+    }
+
+    /// Returns the value attached to the single-label wildcard rule for this node's name, if any.
+    pub fn single_label(&self) -> Option<&V> {
+        self.rule(RuleKind::SingleLabel)
+    }
+
+    /// Returns the value attached to the subdomain wildcard rule for this node's name, if any.
+    pub fn subdomains(&self) -> Option<&V> {
+        self.rule(RuleKind::Subdomains)
+    }
+
+    /// Returns the value attached to the wildcard rule for this node's name with the given depth
+    /// limit, if any.
+    pub fn bounded_wildcard(&self, limit: DepthLimit) -> Option<&V> {
+        self.bounded
+            .iter()
+            .find(|(bounds, _, _)| *bounds == limit)
+            .map(|(_, _, value)| value)
+    }
+
+    /// Returns the value attached to the regex rule with the given source on this node, if any.
+    #[cfg(feature = "regex")]
+    pub fn regex(&self, regex: &str) -> Option<&V> {
+        self.regexes
+            .iter()
+            .find(|(existing, _, _)| existing.as_str() == regex)
+            .map(|(_, _, value)| value)
+    }
 }
 
 impl DomainLookupTree {
@@ -78,42 +405,89 @@ impl DomainLookupTree {
     /// ```
     pub fn new() -> DomainLookupTree {
         DomainLookupTree {
-            nodes: Default::default(),
-            minimum_level: 0,
+            map: DomainLookupMap::new(),
         }
     }
 
-    /// Inserts a domain into the DomainLookupTree.
+    /// Returns a new instance of a DomainLookupTree which validates inserted rules using the given
+    /// [`Profile`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainLookupTree, Profile};
+    ///
+    /// let mut tree = DomainLookupTree::with_profile(Profile::Hostname);
+    /// assert!(tree.insert("_sip._tcp.example.com").is_err());
+    /// ```
+    pub fn with_profile(profile: Profile) -> DomainLookupTree {
+        DomainLookupTree {
+            map: DomainLookupMap::with_profile(profile),
+        }
+    }
+
+    /// Inserts a domain into the DomainLookupTree, returning whether it was newly added. Exact and
+    /// wildcard rules for the same name are stored independently of each other, in any order.
     ///
     /// # Arguments
     ///
-    /// * `domain` - The domain to be inserted into the DLT. Denote as a wildcard by adding a leading dot (.)
+    /// * `domain` - The domain to be inserted into the DLT. Denote as a wildcard by adding a leading dot (.),
+    ///   as a wildcard that excludes the name itself by adding a leading "+." or as a single-label
+    ///   wildcard by adding a leading "*.". Wildcards may end in a [`DepthLimit`] such as "{1,2}".
+    ///   Denote as an exception by adding a leading "!"
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PatternError`] if `domain` is not a valid [`DomainPattern`] under the tree's
+    /// [`Profile`], or if it breaks a constraint the tree was built with, see
+    /// [`DomainLookupBuilder`].
     ///
     /// # Examples
     ///
     /// ```
-    /// use domain_lookup_tree::DomainLookupTree;
+    /// use domain_lookup_tree::{DomainLookupTree, PatternError};
     ///
     /// let mut tree = DomainLookupTree::new();
-    /// tree.insert(".google.com");
+    /// assert_eq!(tree.insert(".google.com"), Ok(true));
+    /// assert_eq!(tree.insert("google.com"), Ok(true));
+    /// assert_eq!(tree.insert(".google.com"), Ok(false));
+    /// assert_eq!(tree.insert("google..com"), Err(PatternError::EmptyLabel));
     /// ```
-    pub fn insert(&mut self, domain: &str) {
-        let is_wildcard = domain.starts_with(".");
-        let segments = domain_to_rseg(domain);
-        let n_segments = segments.len();
-
-        let mut head = &mut self.nodes;
-        for (i, segment) in segments.iter().copied().enumerate() {
-            let node = head
-                .entry(segment.to_owned())
-                .or_insert_with(|| Node::new(i == n_segments - 2 && is_wildcard, segment));
-
-            if i == n_segments - 2 && is_wildcard {
-                return;
-            }
+    pub fn insert(&mut self, domain: &str) -> Result<bool, PatternError> {
+        self.map
+            .insert(domain, ())
+            .map(|previous| previous.is_none())
+    }
 
-            head = &mut node.nodes;
-        }
+    /// Loads the names of a hosts file, such as "0.0.0.0 ads.example.com # comment", into the
+    /// tree as exact rules. Comments, blank lines and local names such as "localhost" are
+    /// skipped, and a line may list several names after its address.
+    ///
+    /// Malformed entries don't stop the import; every one of them is reported instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The hosts file to read
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from `reader` fails. Otherwise returns a [`HostsError`] for
+    /// every address or name that could not be loaded, in the order they appear.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupTree;
+    ///
+    /// let hosts = "127.0.0.1 localhost\n0.0.0.0 ads.example.com tracker.example.net # ads\n";
+    /// let mut tree = DomainLookupTree::new();
+    /// let errors = tree.load_hosts(hosts.as_bytes()).unwrap();
+    /// assert!(errors.is_empty());
+    /// assert_eq!(tree.lookup("tracker.example.net"), Some("tracker.example.net".to_string()));
+    /// assert_eq!(tree.lookup("localhost"), None);
+    /// ```
+    pub fn load_hosts<R: BufRead>(&mut self, reader: R) -> io::Result<Vec<HostsError>> {
+        hosts::load(reader, |name| self.insert(name).map(drop))
     }
 
     /// Looks up a domain in the tree, returns an Option with the matched string including wildcard prefix
@@ -129,60 +503,1036 @@ impl DomainLookupTree {
     /// use domain_lookup_tree::DomainLookupTree;
     ///
     /// let mut tree = DomainLookupTree::new();
-    /// tree.insert(".google.com");
+    /// tree.insert(".google.com").unwrap();
     /// assert_eq!(tree.lookup("www.google.com"), Some(".google.com".to_string()))
     /// ```
     pub fn lookup(&self, domain: &str) -> Option<String> {
-        match self.traverse(domain) {
-            None => None,
-            Some((fqdn, node)) => {
-                return Some(format!("{}{}", if node.wildcard { "." } else { "" }, fqdn))
+        self.lookup_match(domain).map(Match::into_rule)
+    }
+
+    /// Like [`lookup`](Self::lookup), but refuses domains with fewer labels than the tree's
+    /// [minimum level](DomainLookupBuilder::minimum_level) instead of looking them up.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PatternError::BelowMinimumLevel`] if `domain` is too short.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainLookupBuilder, PatternError};
+    ///
+    /// let mut tree = DomainLookupBuilder::new().minimum_level(2).build_tree();
+    /// tree.insert(".google.com").unwrap();
+    /// assert_eq!(tree.try_lookup("www.google.com"), Ok(Some(".google.com".to_string())));
+    /// assert_eq!(
+    ///     tree.try_lookup("com"),
+    ///     Err(PatternError::BelowMinimumLevel { labels: 1, minimum: 2 })
+    /// );
+    /// ```
+    pub fn try_lookup(&self, domain: &str) -> Result<Option<String>, PatternError> {
+        self.map.check_level(domain)?;
+        Ok(self.lookup(domain))
+    }
+
+    /// Looks up a domain in the tree, returning a [`Match`] describing the most specific matching
+    /// rule
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain to look up in the tree
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainLookupTree, RuleKind};
+    ///
+    /// let mut tree = DomainLookupTree::new();
+    /// tree.insert(".google.com").unwrap();
+    /// let m = tree.lookup_match("mail.eu.google.com").unwrap();
+    /// assert_eq!(m.rule(), ".google.com");
+    /// assert_eq!(m.kind(), RuleKind::Wildcard);
+    /// assert_eq!(m.depth(), 2);
+    /// assert_eq!(m.unmatched(), "mail.eu");
+    /// ```
+    pub fn lookup_match(&self, domain: &str) -> Option<Match<'_, ()>> {
+        self.map.lookup_match(domain)
+    }
+
+    /// Looks up a domain in the tree, telling apart domains that no rule matches from domains
+    /// whose most specific match is an exception
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain to look up in the tree
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainLookupTree, Verdict};
+    ///
+    /// let mut tree = DomainLookupTree::new();
+    /// tree.insert(".ads.example.com").unwrap();
+    /// tree.insert("!good.ads.example.com").unwrap();
+    /// assert_eq!(tree.lookup("good.ads.example.com"), None);
+    /// match tree.verdict("good.ads.example.com") {
+    ///     Some(Verdict::Excluded(m)) => assert_eq!(m.rule(), "!good.ads.example.com"),
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn verdict(&self, domain: &str) -> Option<Verdict<Match<'_, ()>>> {
+        self.map.verdict(domain)
+    }
+
+    /// Attaches a regex rule to `zone`, returning whether it was newly added. See
+    /// [`DomainLookupMap::insert_regex`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupTree;
+    ///
+    /// let mut tree = DomainLookupTree::new();
+    /// tree.insert_regex("evil.net", "^[a-z0-9]{16}$").unwrap();
+    /// assert_eq!(tree.lookup("0123456789abcdef.evil.net"), Some("/^[a-z0-9]{16}$/.evil.net".to_string()));
+    /// assert_eq!(tree.lookup("www.evil.net"), None);
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn insert_regex(&mut self, zone: &str, regex: &str) -> Result<bool, PatternError> {
+        self.map
+            .insert_regex(zone, regex, ())
+            .map(|previous| previous.is_none())
+    }
+
+    /// Removes a regex rule from `zone`, returning whether it was present.
+    #[cfg(feature = "regex")]
+    pub fn remove_regex(&mut self, zone: &str, regex: &str) -> bool {
+        self.map.remove_regex(zone, regex).is_some()
+    }
+
+    /// Removes a rule from the tree, returning whether it was present. Exact and wildcard rules
+    /// are removed independently, so removing "google.com" leaves ".google.com" in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The rule to be removed, using the same syntax as [`insert`](Self::insert)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupTree;
+    ///
+    /// let mut tree = DomainLookupTree::new();
+    /// tree.insert(".google.com").unwrap();
+    /// assert!(tree.remove(".google.com"));
+    /// assert_eq!(tree.lookup("www.google.com"), None)
+    /// ```
+    pub fn remove(&mut self, domain: &str) -> bool {
+        self.map.remove(domain).is_some()
+    }
+
+    /// Returns an iterator over every rule in the tree, in arbitrary order. Wildcard rules are
+    /// yielded with their leading dot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupTree;
+    ///
+    /// let mut tree = DomainLookupTree::new();
+    /// tree.insert(".google.com").unwrap();
+    /// assert_eq!(tree.iter().collect::<Vec<_>>(), vec![".google.com".to_string()])
+    /// ```
+    pub fn iter(&self) -> Keys<'_, ()> {
+        self.map.keys()
+    }
+
+    /// Returns an iterator over every rule at or below `zone`, in arbitrary order. Rules above the
+    /// zone, such as a wildcard covering it, are not included.
+    ///
+    /// # Arguments
+    ///
+    /// * `zone` - The domain to enumerate rules under. A leading dot is ignored
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupTree;
+    ///
+    /// let mut tree = DomainLookupTree::new();
+    /// tree.insert(".corp.google.com").unwrap();
+    /// tree.insert("twitter.com").unwrap();
+    /// assert_eq!(tree.rules_under(".corp.google.com").collect::<Vec<_>>(), vec![".corp.google.com".to_string()])
+    /// ```
+    pub fn rules_under(&self, zone: &str) -> Keys<'_, ()> {
+        Keys::new(self.map.rules_under(zone))
+    }
+
+    /// Returns whether there is any rule at or below `zone`, without enumerating them.
+    pub fn has_rules_under(&self, zone: &str) -> bool {
+        self.map.has_rules_under(zone)
+    }
+
+    /// Looks up a domain in the tree, returning every matching rule ordered from most to least
+    /// specific.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain to look up in the tree
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupTree;
+    ///
+    /// let mut tree = DomainLookupTree::new();
+    /// tree.insert(".google.com").unwrap();
+    /// tree.insert("mail.google.com").unwrap();
+    /// assert_eq!(tree.lookup_all("mail.google.com"), vec!["mail.google.com", ".google.com"])
+    /// ```
+    pub fn lookup_all(&self, domain: &str) -> Vec<String> {
+        self.map
+            .lookup_all(domain)
+            .into_iter()
+            .map(Match::into_rule)
+            .collect()
+    }
+
+    pub fn traverse(&self, domain: &str) -> Option<(String, &Node<()>)> {
+        self.map.traverse(domain)
+    }
+}
+
+impl<V> DomainLookupMap<V> {
+    /// Returns a new, empty DomainLookupMap
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    /// let mut map: DomainLookupMap<u32> = DomainLookupMap::new();
+    /// ```
+    pub fn new() -> DomainLookupMap<V> {
+        Self::with_profile(Profile::default())
+    }
+
+    /// Returns a new, empty DomainLookupMap which validates inserted rules using the given
+    /// [`Profile`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainLookupMap, Profile};
+    /// let mut map: DomainLookupMap<u32> = DomainLookupMap::with_profile(Profile::Hostname);
+    /// ```
+    pub fn with_profile(profile: Profile) -> DomainLookupMap<V> {
+        DomainLookupBuilder::new().profile(profile).build()
+    }
+
+    /// Returns the [`Profile`] inserted rules are validated with.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Returns the least number of labels an inserted rule must have, see
+    /// [`DomainLookupBuilder::minimum_level`].
+    pub fn minimum_level(&self) -> usize {
+        self.minimum_level
+    }
+
+    /// Inserts a rule into the map, attaching `value` to it. If the rule was already present, its
+    /// previous value is returned. Exact and wildcard rules for the same name are stored
+    /// independently of each other, in any order.
+    ///
+    /// Rules are matched case-insensitively and a trailing dot is ignored, but iteration and
+    /// [`Match`] results report each rule with the casing it was first inserted with.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The rule to be inserted. Denote as a wildcard by adding a leading dot (.), as a
+    ///   wildcard that excludes the name itself by adding a leading "+." or as a single-label
+    ///   wildcard by adding a leading "*.". Wildcards may end in a [`DepthLimit`] such as "{1,2}".
+    ///   Denote as an exception by adding a leading "!"
+    /// * `value` - The value to return when a lookup matches this rule
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PatternError`] if `domain` is not a valid [`DomainPattern`] under the map's
+    /// [`Profile`], or if it breaks a constraint the map was built with, see
+    /// [`DomainLookupBuilder`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// assert_eq!(map.insert(".google.com", "search"), Ok(None));
+    /// assert_eq!(map.insert(".google.com", "ads"), Ok(Some("search")));
+    /// assert_eq!(map.insert("google.com", "home"), Ok(None));
+    /// ```
+    pub fn insert(&mut self, domain: &str, value: V) -> Result<Option<V>, PatternError> {
+        let pattern = DomainPattern::parse(domain, self.profile)?;
+        self.validate(&pattern)?;
+        let node = self.node_mut(&pattern);
+
+        Ok(node.insert_rule(pattern.kind(), pattern.limit(), pattern.name(), value))
+    }
+
+    /// Attaches a regex rule to `zone`, with `value` attached to it. If a rule with the same
+    /// regex was already attached to the zone, its previous value is returned.
+    ///
+    /// The regex is only evaluated for domains below the zone, against the lowercased labels
+    /// left of it, e.g. "a.b" for "a.b.evil.net" under "evil.net". It is not anchored, so use
+    /// "^" and "$" to match those labels as a whole. A regex rule counts as matching the whole
+    /// domain, at the depth of its zone.
+    ///
+    /// # Arguments
+    ///
+    /// * `zone` - The name to attach the regex to. Denote as an exception by adding a leading "!"
+    /// * `regex` - The regular expression to match the labels below the zone against
+    /// * `value` - The value to return when a lookup matches this rule
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PatternError`] if `zone` is not a valid plain name, with
+    /// [`PatternError::InvalidRegex`] for wildcard zones and regexes that fail to compile, or
+    /// [`PatternError::PublicSuffixWildcard`] if the map rejects wildcards on a public suffix and
+    /// `zone` is one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert_regex("evil.net", r"^[a-z0-9]{16}$", "dga").unwrap();
+    /// let m = map.lookup_match("0123456789abcdef.evil.net").unwrap();
+    /// assert_eq!(m.value(), &"dga");
+    /// assert_eq!(m.unmatched(), "0123456789abcdef");
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn insert_regex(
+        &mut self,
+        zone: &str,
+        regex: &str,
+        value: V,
+    ) -> Result<Option<V>, PatternError> {
+        let pattern = Self::regex_zone(zone, self.profile)?;
+        self.validate(&pattern)?;
+        if let Some(list) = &self.public_suffixes {
+            list.check_zone(&pattern)?;
+        }
+        let regex = regex_rules::compile(regex)?;
+        let node = self.node_mut(&pattern);
+
+        Ok(node.insert_regex(regex, pattern.name(), value))
+    }
+
+    /// Removes a regex rule from `zone`, returning its value if it was present.
+    ///
+    /// # Arguments
+    ///
+    /// * `zone` - The name the regex is attached to, using the same syntax as
+    ///   [`insert_regex`](Self::insert_regex)
+    /// * `regex` - The regular expression, exactly as it was inserted
+    #[cfg(feature = "regex")]
+    pub fn remove_regex(&mut self, zone: &str, regex: &str) -> Option<V> {
+        let pattern = Self::regex_zone(zone, self.profile).ok()?;
+
+        let key = pattern.key();
+        remove_from(
+            self.tree_mut(pattern.is_exception()),
+            &domain_to_rseg(&key),
+            |node| node.remove_regex(regex),
+        )
+    }
+
+    /// Applies the checks configured through [`DomainLookupBuilder`] to a parsed rule.
+    fn validate(&self, pattern: &DomainPattern) -> Result<(), PatternError> {
+        self.check_level(pattern.name())?;
+        if let Some(list) = &self.public_suffixes {
+            list.check(pattern)?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses the zone of a regex rule, which must be a plain name, optionally marked as an
+    /// exception.
+    #[cfg(feature = "regex")]
+    fn regex_zone(zone: &str, profile: Profile) -> Result<DomainPattern, PatternError> {
+        let pattern = DomainPattern::parse(zone, profile)?;
+        if pattern.kind() != RuleKind::Exact || pattern.limit().is_some() {
+            return Err(PatternError::InvalidRegex(format!(
+                "zone {:?} is not a plain name",
+                zone
+            )));
+        }
+
+        Ok(pattern)
+    }
+
+    /// Returns the node for the name of `pattern`, creating it and the path to it if needed.
+    fn node_mut(&mut self, pattern: &DomainPattern) -> &mut Node<V> {
+        let segments = pattern.segments();
+        // A valid pattern always has at least one label
+        let (first, rest) = segments.split_first().expect("pattern has no labels");
+
+        let mut node = self.tree_mut(pattern.is_exception()).get_or_insert(first);
+        for segment in rest {
+            node = node.nodes.get_or_insert(segment);
+        }
+
+        node
+    }
+
+    /// Removes a rule from the map, returning its value if it was present. Exact and wildcard
+    /// rules are removed independently. Any nodes left without a rule or descendants are pruned
+    /// from the tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The rule to be removed, using the same syntax as [`insert`](Self::insert)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert(".google.com", "search").unwrap();
+    /// assert_eq!(map.remove("google.com"), None);
+    /// assert_eq!(map.remove(".google.com"), Some("search"));
+    /// ```
+    pub fn remove(&mut self, domain: &str) -> Option<V> {
+        // A rule that isn't valid can never have been inserted
+        let pattern = DomainPattern::parse(domain, self.profile).ok()?;
+
+        let key = pattern.key();
+        remove_from(
+            self.tree_mut(pattern.is_exception()),
+            &domain_to_rseg(&key),
+            |node| node.remove_rule(pattern.kind(), pattern.limit()),
+        )
+    }
+
+    /// Looks up a domain in the map, returning the value attached to the most specific matching
+    /// rule. If that is an exception, the domain is not matched.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain to look up in the map
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert(".google.com", "search").unwrap();
+    /// assert_eq!(map.lookup("www.google.com"), Some(&"search"))
+    /// ```
+    pub fn lookup(&self, domain: &str) -> Option<&V> {
+        self.lookup_match(domain).map(|m| m.value())
+    }
+
+    /// Like [`lookup`](Self::lookup), but refuses domains with fewer labels than the map's
+    /// [minimum level](DomainLookupBuilder::minimum_level) instead of looking them up.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PatternError::BelowMinimumLevel`] if `domain` is too short.
+    pub fn try_lookup(&self, domain: &str) -> Result<Option<&V>, PatternError> {
+        self.check_level(domain)?;
+        Ok(self.lookup(domain))
+    }
+
+    /// Checks that `domain` has at least as many labels as the minimum level.
+    fn check_level(&self, domain: &str) -> Result<(), PatternError> {
+        let labels = strip_root(domain).split('.').count();
+        if labels < self.minimum_level {
+            return Err(PatternError::BelowMinimumLevel {
+                labels,
+                minimum: self.minimum_level,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Looks up a domain in the map, returning a [`Match`] describing the most specific matching
+    /// rule and its value
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain to look up in the map
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainLookupMap, RuleKind};
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert("mail.google.com", "mail").unwrap();
+    /// let m = map.lookup_match("mail.google.com").unwrap();
+    /// assert_eq!(m.rule(), "mail.google.com");
+    /// assert_eq!(m.kind(), RuleKind::Exact);
+    /// assert_eq!(m.value(), &"mail");
+    /// ```
+    pub fn lookup_match(&self, domain: &str) -> Option<Match<'_, V>> {
+        self.find(domain)
+            .map(|candidate| candidate.into_match(strip_root(domain)))
+    }
+
+    /// Looks up a domain in the map, telling apart domains that no rule matches from domains
+    /// whose most specific match is an exception. An exception overrides rules written for a
+    /// name or zone at most as deep as its own, except an exact rule for the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain to look up in the map
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainLookupMap, Verdict};
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert(".ads.example.com", "block").unwrap();
+    /// map.insert("!good.ads.example.com", "allow").unwrap();
+    /// assert!(matches!(map.verdict("bad.ads.example.com"), Some(Verdict::Matched(m)) if m.value() == &"block"));
+    /// assert!(matches!(map.verdict("good.ads.example.com"), Some(Verdict::Excluded(m)) if m.value() == &"allow"));
+    /// assert!(map.verdict("example.com").is_none());
+    /// ```
+    pub fn verdict(&self, domain: &str) -> Option<Verdict<Match<'_, V>>> {
+        let domain_name = strip_root(domain);
+        self.decide(domain)
+            .map(|verdict| verdict.map(|candidate| candidate.into_match(domain_name)))
+    }
+
+    /// Looks up a domain in the map, returning a [`Match`] for every matching rule, ordered from
+    /// most to least specific. Rules that a matching exception overrides, see
+    /// [`verdict`](Self::verdict), are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain to look up in the map
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert(".google.com", "org").unwrap();
+    /// map.insert(".mail.google.com", "team").unwrap();
+    /// let matches = map.lookup_all("inbox.mail.google.com");
+    /// assert_eq!(matches.iter().map(|m| m.value()).collect::<Vec<_>>(), vec![&"team", &"org"])
+    /// ```
+    pub fn lookup_all(&self, domain: &str) -> Vec<Match<'_, V>> {
+        let (mut candidates, exceptions) = self.candidates(domain);
+        if let Some(exception) = strongest(exceptions) {
+            candidates.retain(|candidate| candidate.precedence() > exception.precedence());
+        }
+        // The sort is stable, so equally specific rules stay in the order they were found
+        candidates.sort_by_key(|candidate| Reverse(candidate.specificity()));
+
+        let domain = strip_root(domain);
+        candidates
+            .into_iter()
+            .map(|candidate| candidate.into_match(domain))
+            .collect()
+    }
+
+    /// Returns an iterator over every rule in the map and its value, in arbitrary order. Wildcard
+    /// rules are yielded with their leading dot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert(".google.com", "search").unwrap();
+    /// assert_eq!(map.iter().collect::<Vec<_>>(), vec![(".google.com".to_string(), &"search")])
+    /// ```
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter::new(self)
+    }
+
+    /// Returns an iterator over every rule in the map and a mutable reference to its value, in
+    /// arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert(".google.com", 1).unwrap();
+    /// for (_, value) in map.iter_mut() {
+    ///     *value += 1;
+    /// }
+    /// assert_eq!(map.lookup("google.com"), Some(&2))
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, V> {
+        IterMut::new(self)
+    }
+
+    /// Returns an iterator over every rule in the map, in arbitrary order.
+    pub fn keys(&self) -> Keys<'_, V> {
+        Keys::new(self.iter())
+    }
+
+    /// Returns an iterator over every value in the map, in arbitrary order.
+    pub fn values(&self) -> Values<'_, V> {
+        Values::new(self.iter())
+    }
+
+    /// Returns an iterator over every rule at or below `zone` and its value, in arbitrary order,
+    /// exceptions included. Rules above the zone, such as a wildcard covering it, are not
+    /// included.
+    ///
+    /// # Arguments
+    ///
+    /// * `zone` - The domain to enumerate rules under. A leading dot is ignored
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert(".google.com", "search").unwrap();
+    /// map.insert("mail.google.com", "mail").unwrap();
+    /// map.insert("twitter.com", "social").unwrap();
+    /// assert_eq!(map.rules_under("mail.google.com").collect::<Vec<_>>(), vec![("mail.google.com".to_string(), &"mail")])
+    /// ```
+    pub fn rules_under(&self, zone: &str) -> Iter<'_, V> {
+        let start = [(&self.exceptions, true), (&self.nodes, false)]
+            .iter()
+            .filter_map(|(nodes, exception)| zone_node(nodes, zone).map(|node| (*exception, node)))
+            .collect();
+
+        Iter::under(start)
+    }
+
+    /// Returns whether there is any rule at or below `zone`, without enumerating them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::DomainLookupMap;
+    ///
+    /// let mut map = DomainLookupMap::new();
+    /// map.insert("a.corp.google.com", ()).unwrap();
+    /// assert!(map.has_rules_under(".corp.google.com"));
+    /// assert!(!map.has_rules_under("twitter.com"));
+    /// ```
+    pub fn has_rules_under(&self, zone: &str) -> bool {
+        // Nodes only exist on the path to a rule, and are pruned once they no longer lead to one,
+        // so finding the zone's node is enough.
+        zone_node(&self.nodes, zone).is_some() || zone_node(&self.exceptions, zone).is_some()
+    }
+
+    /// Returns the tree exception rules or regular rules are stored in.
+    fn tree_mut(&mut self, exception: bool) -> &mut NodeList<V> {
+        if exception {
+            &mut self.exceptions
+        } else {
+            &mut self.nodes
+        }
+    }
+
+    /// Walks the tree for `domain`, returning the node carrying the most specific matching rule
+    /// along with the name that rule was inserted with. For wildcard matches this is the name of
+    /// the wildcard rule itself, not of the deepest node visited on the way. Nothing is returned
+    /// if the domain is excluded by an exception.
+    pub fn traverse(&self, domain: &str) -> Option<(String, &Node<V>)> {
+        self.find(domain)
+            .map(|candidate| (candidate.name.to_owned(), candidate.node))
+    }
+
+    /// Walks the tree for `domain`, returning the most specific matching rule unless an
+    /// exception overrides it.
+    fn find(&self, domain: &str) -> Option<Candidate<'_, V>> {
+        match self.decide(domain)? {
+            Verdict::Matched(candidate) => Some(candidate),
+            Verdict::Excluded(_) => None,
+        }
+    }
+
+    /// Walks both trees for `domain`, weighing the matching rules against the strongest matching
+    /// exception. An exception only overrides rules written for a name at most as deep as its
+    /// own, so that it can't undo a more specific rule inside the hole it carves.
+    fn decide(&self, domain: &str) -> Option<Verdict<Candidate<'_, V>>> {
+        let (mut rules, exceptions) = self.candidates(domain);
+        if rules.is_empty() {
+            return None;
+        }
+
+        if let Some(exception) = strongest(exceptions) {
+            rules.retain(|rule| rule.precedence() > exception.precedence());
+            if rules.is_empty() {
+                return Some(Verdict::Excluded(exception));
             }
         }
+        most_specific(rules).map(Verdict::Matched)
+    }
+
+    /// Walks both trees for `domain`, returning every matching rule and every matching exception,
+    /// each in the order they were found.
+    fn candidates(&self, domain: &str) -> (Vec<Candidate<'_, V>>, Vec<Candidate<'_, V>>) {
+        let key = normalize(strip_root(domain));
+        let segments = domain_to_rseg(&key);
+        let mut rules = Vec::new();
+        let mut exceptions = Vec::new();
+
+        collect_rules(&self.nodes, &segments, false, &mut rules);
+        if !self.exceptions.is_empty() {
+            collect_rules(&self.exceptions, &segments, true, &mut exceptions);
+        }
+        (rules, exceptions)
+    }
+}
+
+impl<V> Default for DomainLookupMap<V> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    pub fn traverse(&self, domain: &str) -> Option<(String, &Node)> {
-        let segments = domain_to_rseg(domain);
-        let mut wildcard_match = None;
-        // We start the traversal at the root
-        let mut head: &NodeList = &self.nodes;
-
-        let mut fqdn = String::new();
-
-        // We traverse the tree in level-reverse order
-        for (i, segment) in segments.iter().copied().enumerate() {
-            // Now we look up the children of the latest matched node
-            // If this is the first iteration, then it's the root NodeList
-            if let Some(child) = head.get(segment) {
-                fqdn = format!(
-                    "{}{}{}",
-                    segment.to_owned(),
-                    if i == 0 { "" } else { "." },
-                    fqdn
-                );
-                head = &child.nodes;
-                // We have exhausted the traversal. If the traversal depth is equal to the segment
-                // length, then we've found an absolute match!
-                if i == segments.len() - 1 {
-                    return Some((fqdn, child));
-                } else if child.wildcard {
-                    // Current node is wildcard, so we now 100% have a value to return
-                    wildcard_match = Some(child);
+/// A rule found while walking the tree for a domain.
+struct Candidate<'a, V> {
+    /// The name of the rule, as it was inserted.
+    name: &'a str,
+    node: &'a Node<V>,
+    exception: bool,
+    kind: RuleKind,
+    limit: Option<DepthLimit>,
+    #[cfg(feature = "regex")]
+    regex: Option<&'a regex::Regex>,
+    depth: usize,
+    value: &'a V,
+}
+
+impl<'a, V> Candidate<'a, V> {
+    /// Rules matching the whole domain, i.e. exact and single-label wildcard rules, are more
+    /// specific than wildcard rules, and deeper rules are more specific than shallower ones.
+    fn specificity(&self) -> (bool, usize) {
+        let partial = matches!(self.kind, RuleKind::Wildcard | RuleKind::Subdomains);
+        (!partial, self.depth)
+    }
+
+    /// Rules are weighed against exceptions by the depth of the name or zone they are written
+    /// for, e.g. 3 for "*.ads.example.com". Exact rules for a literal name win ties, while
+    /// exceptions are ranked by depth alone, so even an exact exception yields to an exact rule
+    /// for the same name.
+    fn precedence(&self) -> (usize, bool) {
+        let depth = match self.kind {
+            // The depth of a single-label match includes the label below the name
+            RuleKind::SingleLabel => self.depth - 1,
+            _ => self.depth,
+        };
+        // Exact rules with wildcard labels, such as "test.*", only happen to match the name
+        let literal = self.kind == RuleKind::Exact && !self.name.contains(['*', '?']);
+        (depth, literal && !self.exception)
+    }
+
+    /// Returns whether both candidates stem from the same rule.
+    fn is_same_rule(&self, other: &Self) -> bool {
+        #[cfg(feature = "regex")]
+        {
+            let regex = |candidate: &Self| candidate.regex.map(|regex| regex as *const _);
+            if regex(self) != regex(other) {
+                return false;
+            }
+        }
+
+        std::ptr::eq(self.node, other.node) && self.kind == other.kind && self.limit == other.limit
+    }
+
+    fn into_match(self, domain: &str) -> Match<'a, V> {
+        #[cfg(feature = "regex")]
+        {
+            if let Some(regex) = self.regex {
+                let rule = regex_rules::rule_name(self.name, regex, self.exception);
+                return Match::new(domain, rule, self.kind, self.depth, self.value);
+            }
+        }
+
+        let rule = rule_name(self.name, self.kind, self.limit, self.exception);
+        Match::new(domain, rule, self.kind, self.depth, self.value)
+    }
+}
+
+/// Returns the most specific of `candidates`. Among equally specific rules, the first one found
+/// wins; as literal labels are tried before wildcard labels, this prefers the rule whose rightmost
+/// wildcard label is furthest to the left.
+fn most_specific<V>(candidates: Vec<Candidate<'_, V>>) -> Option<Candidate<'_, V>> {
+    candidates
+        .into_iter()
+        .fold(None, |best, candidate| match best {
+            Some(best) if best.specificity() >= candidate.specificity() => Some(best),
+            _ => Some(candidate),
+        })
+}
+
+/// Returns the exception which overrides the most rules, preferring the earliest on ties.
+fn strongest<V>(exceptions: Vec<Candidate<'_, V>>) -> Option<Candidate<'_, V>> {
+    exceptions
+        .into_iter()
+        .fold(None, |best, exception| match best {
+            Some(best) if best.precedence() >= exception.precedence() => Some(best),
+            _ => Some(exception),
+        })
+}
+
+/// Collects every rule in the tree rooted at `nodes` that matches the reversed `segments`.
+fn collect_rules<'a, V>(
+    nodes: &'a NodeList<V>,
+    segments: &[&str],
+    exception: bool,
+    candidates: &mut Vec<Candidate<'a, V>>,
+) {
+    // We start the traversal at the root
+    collect_candidates(nodes, segments, 0, exception, candidates);
+
+    // A wildcard label on the right, as in "example.*", stands for any suffix. The regular walk
+    // only lets it match a single label, so try it against every longer suffix as well.
+    if let Some(any_suffix) = nodes.get(WILDCARD_LABEL) {
+        for depth in 2..=segments.len() {
+            let rest = &segments[depth..];
+            visit_node(any_suffix, rest, depth, exception, candidates);
+        }
+
+        // Such a rule may match at several suffix lengths, e.g. ".example.*" for
+        // "example.example.com". Only its most specific match is kept.
+        let mut i = 0;
+        while i < candidates.len() {
+            match candidates[..i]
+                .iter()
+                .position(|found| found.is_same_rule(&candidates[i]))
+            {
+                Some(j) => {
+                    if candidates[i].depth > candidates[j].depth {
+                        candidates.swap(i, j);
+                    }
+                    candidates.remove(i);
                 }
-            } else {
-                // We have exhausted the traversal.
-                break;
+                None => i += 1,
             }
         }
+    }
+}
 
-        if let Some(m) = wildcard_match {
-            Some((fqdn, m))
-        } else {
-            None
+/// Collects every rule below `nodes` that matches the reversed `segments`, where `depth` is the
+/// number of labels of the name `nodes` are the children of and `exception` whether `nodes` hold
+/// exception rules.
+///
+/// We traverse the tree in level-reverse order. At every level the child for the literal label,
+/// the children for glob labels matching it and the wildcard label child, which matches any
+/// single label, are all tried, in that order. Equally specific rules found earlier take
+/// precedence, so a literal label beats a glob, which beats the wildcard label.
+fn collect_candidates<'a, V>(
+    nodes: &'a NodeList<V>,
+    segments: &[&str],
+    depth: usize,
+    exception: bool,
+    candidates: &mut Vec<Candidate<'a, V>>,
+) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        // We have exhausted the traversal.
+        None => return,
+    };
+    let depth = depth + 1;
+    // A label that is spelled like a glob, such as "cdn-*", already reaches the glob's node
+    // through the globs it matches
+    let literal = nodes
+        .get(segment)
+        .filter(|_| *segment != WILDCARD_LABEL && !glob::is_glob(segment));
+
+    let globs = nodes.globs_matching(segment);
+
+    for child in literal
+        .into_iter()
+        .chain(globs)
+        .chain(nodes.get(WILDCARD_LABEL))
+    {
+        visit_node(child, rest, depth, exception, candidates);
+    }
+}
+
+/// Collects the rules on `node` and below it that match, where `rest` are the reversed segments
+/// left below the node and `depth` the number of labels matched so far.
+fn visit_node<'a, V>(
+    node: &'a Node<V>,
+    rest: &[&str],
+    depth: usize,
+    exception: bool,
+    candidates: &mut Vec<Candidate<'a, V>>,
+) {
+    let candidate = |name, kind, limit, depth, value| Candidate {
+        name,
+        node,
+        exception,
+        kind,
+        limit,
+        #[cfg(feature = "regex")]
+        regex: None,
+        depth,
+        value,
+    };
+
+    // If the traversal depth is equal to the segment length and an exact rule was inserted for
+    // this name, then we've found an absolute match! Nodes that merely lead to deeper rules don't
+    // count.
+    if let Some((name, value)) = node.entry(RuleKind::Exact).filter(|_| rest.is_empty()) {
+        candidates.push(candidate(name, RuleKind::Exact, None, depth, value));
+    }
+    // A wildcard with a depth limit covers the domain if the number of labels left below it is
+    // within the limit. Being narrower, these are tried before the unbounded wildcard.
+    for (limit, name, value) in &node.bounded {
+        if limit.contains(rest.len()) {
+            candidates.push(candidate(
+                name,
+                RuleKind::Wildcard,
+                Some(*limit),
+                depth,
+                value,
+            ));
+        }
+    }
+    // Any wildcard on the way covers the domain, even if we descend past it.
+    if let Some((name, value)) = node.entry(RuleKind::Wildcard) {
+        candidates.push(candidate(name, RuleKind::Wildcard, None, depth, value));
+    }
+    // A subdomain wildcard does too, but only once there is at least one label left below it.
+    if let Some((name, value)) = node
+        .entry(RuleKind::Subdomains)
+        .filter(|_| !rest.is_empty())
+    {
+        candidates.push(candidate(name, RuleKind::Subdomains, None, depth, value));
+    }
+
+    // Regexes are only evaluated once a domain has reached their node, against what is left of
+    // it.
+    #[cfg(feature = "regex")]
+    {
+        if !rest.is_empty() && !node.regexes.is_empty() {
+            let unmatched = regex_rules::unmatched(rest);
+            for (regex, name, value) in &node.regexes {
+                if regex.is_match(&unmatched) {
+                    candidates.push(Candidate {
+                        regex: Some(regex),
+                        ..candidate(name, RuleKind::Regex, None, depth, value)
+                    });
+                }
+            }
+        }
+    }
+
+    collect_candidates(&node.nodes, rest, depth, exception, candidates);
+
+    // A single-label wildcard only covers the domain if exactly one label is left, which it then
+    // matches as well. It acts like a wildcard label child, so it is tried after the literal
+    // children.
+    if let Some((name, value)) = node
+        .entry(RuleKind::SingleLabel)
+        .filter(|_| rest.len() == 1)
+    {
+        candidates.push(candidate(
+            name,
+            RuleKind::SingleLabel,
+            None,
+            depth + 1,
+            value,
+        ));
+    }
+}
+
+/// Walks down `nodes` along the reversed `segments` and removes a rule from the node found at the
+/// end using `remove`, pruning every node on the way back up that no longer carries a rule or
+/// descendants.
+fn remove_from<V>(
+    nodes: &mut NodeList<V>,
+    segments: &[&str],
+    remove: impl FnOnce(&mut Node<V>) -> Option<V>,
+) -> Option<V> {
+    let (segment, rest) = segments.split_first()?;
+    let node = nodes.get_mut(segment)?;
+
+    let removed = if rest.is_empty() {
+        remove(node)
+    } else {
+        remove_from(&mut node.nodes, rest, remove)
+    };
+
+    if node.is_prunable() {
+        nodes.remove(segment);
+    }
+
+    removed
+}
+
+/// Descends along the reversed segments of `zone`, returning its node if present.
+fn zone_node<'a, V>(nodes: &'a NodeList<V>, zone: &str) -> Option<&'a Node<V>> {
+    let key = normalize(strip_root(zone.strip_prefix('.').unwrap_or(zone)));
+    let segments = domain_to_rseg(&key);
+    let (first, rest) = segments.split_first()?;
+
+    let mut node = nodes.get(first)?;
+    for segment in rest {
+        node = node.nodes.get(segment)?;
+    }
+
+    Some(node)
+}
+
+/// Formats the name of a rule, adding the prefixes for exceptions and wildcards and the suffix
+/// for depth limits.
+fn rule_name(name: &str, kind: RuleKind, limit: Option<DepthLimit>, exception: bool) -> String {
+    let prefix = match kind {
+        RuleKind::Exact => "",
+        RuleKind::Wildcard => ".",
+        RuleKind::SingleLabel => "*.",
+        RuleKind::Subdomains => "+.",
+        #[cfg(feature = "regex")]
+        RuleKind::Regex => unreachable!("regex rules are named after their regex"),
+    };
+    let limit = limit.map(|limit| limit.to_string()).unwrap_or_default();
+    let exception = if exception { "!" } else { "" };
+
+    format!("{}{}{}{}", exception, prefix, name, limit)
+}
+
+/// Strips the trailing dot from an absolute name such as "google.com.", as seen in DNS queries.
+fn strip_root(domain: &str) -> &str {
+    domain.strip_suffix('.').unwrap_or(domain)
+}
+
+/// Brings `domain` into the form the tree is keyed by, only allocating if there is anything to
+/// change. ASCII letters are lowercased and, with the `idna` feature, internationalized names are
+/// converted to A-labels.
+fn normalize(domain: &str) -> Cow<'_, str> {
+    #[cfg(feature = "idna")]
+    {
+        if !domain.is_ascii() {
+            if let Ok(ascii) = idn::to_ascii(domain) {
+                return ascii;
+            }
         }
     }
+
+    if domain.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Owned(domain.to_ascii_lowercase())
+    } else {
+        Cow::Borrowed(domain)
+    }
 }
 
 fn domain_to_rseg(domain: &str) -> Vec<&str> {
-    domain.rsplit(".").collect::<Vec<&str>>()
+    domain.rsplit('.').collect::<Vec<&str>>()
 }
@@ -0,0 +1,27 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+
+/// Calls `f` with the number, starting at 1, and contents of every line of `reader`. Lines which
+/// are not valid UTF-8 are passed as an error holding a lossy copy, so that one bad line doesn't
+/// end the import of a whole list.
+pub(crate) fn read_lines<R, F>(mut reader: R, mut f: F) -> io::Result<()>
+where
+    R: BufRead,
+    F: FnMut(usize, Result<&str, Cow<'_, str>>),
+{
+    let mut buf = Vec::new();
+    let mut line_number = 0;
+
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            return Ok(());
+        }
+        line_number += 1;
+
+        match std::str::from_utf8(&buf) {
+            Ok(line) => f(line_number, Ok(line)),
+            Err(_) => f(line_number, Err(String::from_utf8_lossy(&buf))),
+        }
+    }
+}
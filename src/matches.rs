@@ -0,0 +1,115 @@
+use crate::RuleKind;
+
+/// The result of a successful lookup, describing which rule matched and how.
+///
+/// This `struct` is created by [`DomainLookupMap::lookup_match`](crate::DomainLookupMap::lookup_match)
+/// and [`DomainLookupTree::lookup_match`](crate::DomainLookupTree::lookup_match).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match<'a, V> {
+    rule: String,
+    kind: RuleKind,
+    depth: usize,
+    unmatched: String,
+    value: &'a V,
+}
+
+impl<'a, V> Match<'a, V> {
+    pub(crate) fn new(
+        domain: &str,
+        rule: String,
+        kind: RuleKind,
+        depth: usize,
+        value: &'a V,
+    ) -> Self {
+        Self {
+            rule,
+            kind,
+            depth,
+            unmatched: unmatched_labels(domain, depth).to_owned(),
+            value,
+        }
+    }
+
+    /// Returns the rule that matched, including the leading dot for wildcard rules and any depth
+    /// limit.
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// Consumes the match, returning the rule that matched.
+    pub fn into_rule(self) -> String {
+        self.rule
+    }
+
+    /// Returns the kind of rule that matched.
+    pub fn kind(&self) -> RuleKind {
+        self.kind
+    }
+
+    /// Returns whether the domain was matched by a wildcard rule rather than an exact one.
+    pub fn is_wildcard(&self) -> bool {
+        self.kind != RuleKind::Exact
+    }
+
+    /// Returns the number of labels of the domain that were matched by the rule, e.g. 2 for
+    /// ".google.com".
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the leftmost labels of the looked up domain that were not matched by the rule, e.g.
+    /// "www" when "www.google.com" matches ".google.com". Empty for exact matches.
+    pub fn unmatched(&self) -> &str {
+        &self.unmatched
+    }
+
+    /// Returns the value attached to the rule that matched.
+    pub fn value(&self) -> &'a V {
+        self.value
+    }
+}
+
+/// Strips the rightmost `depth` labels from `domain`, returning what is left.
+fn unmatched_labels(domain: &str, depth: usize) -> &str {
+    let mut end = domain.len();
+    for _ in 0..depth {
+        match domain[..end].rfind('.') {
+            Some(i) => end = i,
+            None => return "",
+        }
+    }
+
+    &domain[..end]
+}
+
+/// The outcome of a lookup that distinguishes exceptions from regular matches.
+///
+/// This `enum` is created by [`DomainLookupMap::verdict`](crate::DomainLookupMap::verdict) and
+/// [`DomainLookupTree::verdict`](crate::DomainLookupTree::verdict), with `T` being a [`Match`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict<T> {
+    /// The domain is matched by a regular rule.
+    Matched(T),
+    /// The domain is matched by a rule, but carved out of it by an exception written for a name
+    /// at least as deep as that of every matching rule. Holds the exception.
+    Excluded(T),
+}
+
+impl<T> Verdict<T> {
+    /// Returns whether the domain is matched by a regular rule.
+    pub fn is_matched(&self) -> bool {
+        matches!(self, Verdict::Matched(_))
+    }
+
+    /// Returns whether the domain is carved out by an exception.
+    pub fn is_excluded(&self) -> bool {
+        matches!(self, Verdict::Excluded(_))
+    }
+
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> Verdict<U> {
+        match self {
+            Verdict::Matched(inner) => Verdict::Matched(f(inner)),
+            Verdict::Excluded(inner) => Verdict::Excluded(f(inner)),
+        }
+    }
+}
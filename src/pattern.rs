@@ -0,0 +1,363 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{domain_to_rseg, normalize, rule_name, strip_root, RuleKind, WILDCARD_LABEL};
+
+/// The longest a single label may be, in bytes.
+const MAX_LABEL_LEN: usize = 63;
+/// The longest a full name may be, in bytes, not counting a wildcard prefix.
+const MAX_NAME_LEN: usize = 253;
+
+/// How strictly the labels of a [`DomainPattern`] are validated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Profile {
+    /// Hostname rules (RFC 1123): labels may only contain ASCII letters, digits and hyphens, and
+    /// may not start or end with a hyphen.
+    Hostname,
+    /// DNS rules: like [`Profile::Hostname`], but also allowing underscores and hyphens anywhere
+    /// in a label, as used by service records such as "_sip._tcp.example.com". This is the
+    /// default.
+    #[default]
+    Dns,
+}
+
+/// The reason a string was rejected as a [`DomainPattern`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatternError {
+    /// The pattern does not contain a name, e.g. "" or ".".
+    Empty,
+    /// The name contains an empty label, e.g. "a..b".
+    EmptyLabel,
+    /// A label is longer than 63 bytes.
+    LabelTooLong(String),
+    /// The name is longer than 253 bytes.
+    NameTooLong(usize),
+    /// A label contains a character not allowed by the [`Profile`] in use.
+    InvalidCharacter(char),
+    /// A depth limit is malformed, e.g. "{2,1}", or given on a rule other than a wildcard.
+    InvalidDepthLimit,
+    /// An internationalized name could not be converted to A-labels. Only returned with the
+    /// `idna` feature enabled.
+    InvalidIdna,
+    /// A regex rule could not be compiled, or its zone is not a plain name. Only returned with
+    /// the `regex` feature enabled.
+    InvalidRegex(String),
+    /// A wildcard rule covers a public suffix, e.g. ".co.uk", and with it names registered by
+    /// unrelated parties. Only returned by maps built with
+    /// [`DomainLookupBuilder::public_suffixes`](crate::DomainLookupBuilder::public_suffixes),
+    /// or by [`PublicSuffixList::check`](crate::PublicSuffixList::check).
+    PublicSuffixWildcard(String),
+    /// The name has fewer labels than the minimum level set with
+    /// [`DomainLookupBuilder::minimum_level`](crate::DomainLookupBuilder::minimum_level), e.g.
+    /// ".com" when at least two labels are required.
+    BelowMinimumLevel {
+        /// The number of labels in the name.
+        labels: usize,
+        /// The least number of labels a name must have.
+        minimum: usize,
+    },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Empty => write!(f, "pattern is empty"),
+            PatternError::EmptyLabel => write!(f, "pattern contains an empty label"),
+            PatternError::LabelTooLong(label) => write!(
+                f,
+                "label \"{}\" is longer than {} bytes",
+                label, MAX_LABEL_LEN
+            ),
+            PatternError::NameTooLong(len) => write!(
+                f,
+                "name is {} bytes long, the maximum is {}",
+                len, MAX_NAME_LEN
+            ),
+            PatternError::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+            PatternError::InvalidDepthLimit => write!(f, "invalid depth limit"),
+            PatternError::InvalidIdna => write!(f, "invalid internationalized domain name"),
+            PatternError::InvalidRegex(reason) => write!(f, "invalid regex rule: {}", reason),
+            PatternError::PublicSuffixWildcard(suffix) => {
+                write!(f, "wildcard rule on public suffix \"{}\"", suffix)
+            }
+            PatternError::BelowMinimumLevel { labels, minimum } => {
+                write!(f, "name has {} labels, the minimum is {}", labels, minimum)
+            }
+        }
+    }
+}
+
+impl Error for PatternError {}
+
+/// Bounds on how many labels below its name a wildcard rule matches, written as a suffix such as
+/// "{1,2}" in ".example.com{1,2}". "{n}" matches exactly n extra labels, "{m,}" at least m and
+/// "{,n}" at most n.
+///
+/// # Examples
+///
+/// ```
+/// use domain_lookup_tree::{DepthLimit, DomainPattern};
+///
+/// let pattern: DomainPattern = ".example.com{1,2}".parse().unwrap();
+/// assert_eq!(pattern.limit(), DepthLimit::new(1, Some(2)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthLimit {
+    min: usize,
+    max: Option<usize>,
+}
+
+impl DepthLimit {
+    /// Returns a limit matching at least `min` and, if given, at most `max` extra labels, or
+    /// `None` if `min` is greater than `max`.
+    pub fn new(min: usize, max: Option<usize>) -> Option<Self> {
+        match max {
+            Some(max) if min > max => None,
+            _ => Some(Self { min, max }),
+        }
+    }
+
+    /// Returns the least number of extra labels matched.
+    pub fn min(&self) -> usize {
+        self.min
+    }
+
+    /// Returns the greatest number of extra labels matched, if bounded.
+    pub fn max(&self) -> Option<usize> {
+        self.max
+    }
+
+    /// Returns whether a name with `extra` labels below the rule's name is matched.
+    pub fn contains(&self, extra: usize) -> bool {
+        extra >= self.min && !matches!(self.max, Some(max) if extra > max)
+    }
+
+    /// Parses the inside of a "{m,n}" suffix.
+    fn parse(bounds: &str) -> Result<Self, PatternError> {
+        fn bound(s: &str) -> Result<Option<usize>, PatternError> {
+            if s.is_empty() {
+                Ok(None)
+            } else if s.bytes().all(|b| b.is_ascii_digit()) {
+                s.parse()
+                    .map(Some)
+                    .map_err(|_| PatternError::InvalidDepthLimit)
+            } else {
+                Err(PatternError::InvalidDepthLimit)
+            }
+        }
+
+        let (min, max) = match bounds.split_once(',') {
+            Some((min, max)) => (bound(min)?.unwrap_or(0), bound(max)?),
+            None => {
+                let exact = bound(bounds)?.ok_or(PatternError::InvalidDepthLimit)?;
+                (exact, Some(exact))
+            }
+        };
+
+        Self::new(min, max).ok_or(PatternError::InvalidDepthLimit)
+    }
+}
+
+impl fmt::Display for DepthLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{{{}}}", max),
+            Some(max) => write!(f, "{{{},{}}}", self.min, max),
+            None => write!(f, "{{{},}}", self.min),
+        }
+    }
+}
+
+/// A validated rule, as accepted by [`DomainLookupTree::insert`](crate::DomainLookupTree::insert)
+/// and [`DomainLookupMap::insert`](crate::DomainLookupMap::insert).
+///
+/// # Examples
+///
+/// ```
+/// use domain_lookup_tree::{DomainPattern, PatternError, RuleKind};
+///
+/// let pattern: DomainPattern = ".google.com".parse().unwrap();
+/// assert_eq!(pattern.kind(), RuleKind::Wildcard);
+/// assert_eq!(pattern.name(), "google.com");
+/// assert_eq!(pattern.to_string(), ".google.com");
+///
+/// assert_eq!("a..b".parse::<DomainPattern>(), Err(PatternError::EmptyLabel));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DomainPattern {
+    exception: bool,
+    kind: RuleKind,
+    name: String,
+    limit: Option<DepthLimit>,
+}
+
+impl DomainPattern {
+    /// Parses and validates a pattern using the given [`Profile`]. A trailing dot, as in the
+    /// absolute name "google.com.", is accepted and dropped. The casing of the pattern is kept.
+    ///
+    /// A leading "!" marks the pattern as an exception, which carves a hole into the rules it
+    /// overlaps with, e.g. "!good.ads.example.com" next to ".ads.example.com".
+    ///
+    /// Wildcard rules may end in a [`DepthLimit`] such as "{1,2}". A limit of "{0,}" matches any
+    /// depth, so it is dropped.
+    ///
+    /// With the `idna` feature, internationalized names such as "bücher.example" are converted to
+    /// their A-label form ("xn--bcher-kva.example") before being validated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainPattern, PatternError, Profile};
+    ///
+    /// assert!(DomainPattern::parse("_sip._tcp.example.com", Profile::Dns).is_ok());
+    /// assert_eq!(
+    ///     DomainPattern::parse("_sip._tcp.example.com", Profile::Hostname),
+    ///     Err(PatternError::InvalidCharacter('_'))
+    /// );
+    /// ```
+    pub fn parse(pattern: &str, profile: Profile) -> Result<Self, PatternError> {
+        let (exception, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        let (kind, name) = if let Some(name) = pattern.strip_prefix('.') {
+            (RuleKind::Wildcard, name)
+        } else if let Some(name) = pattern.strip_prefix("+.") {
+            (RuleKind::Subdomains, name)
+        } else if let Some(name) = pattern.strip_prefix("*.") {
+            (RuleKind::SingleLabel, name)
+        } else {
+            (RuleKind::Exact, pattern)
+        };
+
+        let (name, limit) = match name.strip_suffix('}') {
+            Some(rest) => {
+                let (name, bounds) = rest
+                    .rsplit_once('{')
+                    .ok_or(PatternError::InvalidDepthLimit)?;
+                if kind != RuleKind::Wildcard {
+                    return Err(PatternError::InvalidDepthLimit);
+                }
+                (name, Some(DepthLimit::parse(bounds)?))
+            }
+            None => (name, None),
+        };
+        let limit = limit.filter(|limit| limit.min > 0 || limit.max.is_some());
+        let name = strip_root(name);
+
+        let name = to_ascii(name)?;
+        if name.is_empty() {
+            return Err(PatternError::Empty);
+        }
+        if name.len() > MAX_NAME_LEN {
+            return Err(PatternError::NameTooLong(name.len()));
+        }
+        for label in name.split('.') {
+            validate_label(label, profile)?;
+        }
+
+        Ok(Self {
+            exception,
+            kind,
+            name: name.into_owned(),
+            limit,
+        })
+    }
+
+    /// Returns whether this pattern is an exception, i.e. starts with "!".
+    pub fn is_exception(&self) -> bool {
+        self.exception
+    }
+
+    /// Returns the kind of rule this pattern describes.
+    pub fn kind(&self) -> RuleKind {
+        self.kind
+    }
+
+    /// Returns the depth limit of a wildcard pattern, if it has one.
+    pub fn limit(&self) -> Option<DepthLimit> {
+        self.limit
+    }
+
+    /// Returns the name this pattern applies to, without any wildcard prefix.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the labels of the name in reverse order, which is the order they are stored in the
+    /// tree.
+    pub(crate) fn segments(&self) -> Vec<&str> {
+        domain_to_rseg(&self.name)
+    }
+
+    /// Returns the name in the case-folded form the tree is keyed by.
+    pub(crate) fn key(&self) -> Cow<'_, str> {
+        normalize(&self.name)
+    }
+}
+
+impl FromStr for DomainPattern {
+    type Err = PatternError;
+
+    /// Parses a pattern using the default [`Profile`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, Profile::default())
+    }
+}
+
+impl fmt::Display for DomainPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&rule_name(
+            &self.name,
+            self.kind,
+            self.limit,
+            self.exception,
+        ))
+    }
+}
+
+#[cfg(feature = "idna")]
+fn to_ascii(name: &str) -> Result<Cow<'_, str>, PatternError> {
+    // Leave ASCII names alone rather than lowercasing them, so their casing is kept
+    if name.is_ascii() {
+        return Ok(Cow::Borrowed(name));
+    }
+
+    crate::idn::to_ascii(name).map_err(|_| PatternError::InvalidIdna)
+}
+
+#[cfg(not(feature = "idna"))]
+fn to_ascii(name: &str) -> Result<Cow<'_, str>, PatternError> {
+    Ok(Cow::Borrowed(name))
+}
+
+fn validate_label(label: &str, profile: Profile) -> Result<(), PatternError> {
+    if label.is_empty() {
+        return Err(PatternError::EmptyLabel);
+    }
+    if label == WILDCARD_LABEL {
+        return Ok(());
+    }
+    if label.len() > MAX_LABEL_LEN {
+        return Err(PatternError::LabelTooLong(label.to_owned()));
+    }
+
+    for c in label.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' => {}
+            '_' if profile == Profile::Dns => {}
+            // Glob characters
+            '*' | '?' => {}
+            _ => return Err(PatternError::InvalidCharacter(c)),
+        }
+    }
+
+    if profile == Profile::Hostname && (label.starts_with('-') || label.ends_with('-')) {
+        return Err(PatternError::InvalidCharacter('-'));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,390 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{
+    glob, strip_root, DomainLookupMap, DomainPattern, PatternError, RuleKind, Verdict,
+    WILDCARD_LABEL,
+};
+
+/// The section of the Public Suffix List a rule comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Section {
+    /// Suffixes delegated through ICANN, such as "com" or "co.uk".
+    Icann,
+    /// Suffixes submitted by the owners of a domain, such as "github.io".
+    Private,
+}
+
+/// A rule of a Public Suffix List that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PslError {
+    line: usize,
+    rule: String,
+    error: PatternError,
+}
+
+impl PslError {
+    /// Returns the line the rule is on, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the rule as it appears in the list.
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// Returns the reason the rule was rejected.
+    pub fn error(&self) -> &PatternError {
+        &self.error
+    }
+}
+
+impl fmt::Display for PslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: invalid rule {:?}: {}",
+            self.line, self.rule, self.error
+        )
+    }
+}
+
+impl Error for PslError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The [Public Suffix List](https://publicsuffix.org), which tells the suffixes under which
+/// names can be registered, such as "com", "co.uk" or "github.io".
+///
+/// Its rules are kept in a [`DomainLookupMap`], where a rule such as "co.uk" is stored as the
+/// wildcard ".co.uk", "*.ck" as ".*.ck" and the exception "!www.ck" as "!.www.ck". The public
+/// suffix of a domain is then the name of the rule it matches.
+///
+/// # Examples
+///
+/// ```
+/// use domain_lookup_tree::PublicSuffixList;
+///
+/// let list = PublicSuffixList::parse("com\nuk\nco.uk\n*.ck\n!www.ck").unwrap();
+/// assert_eq!(list.public_suffix("www.example.co.uk"), Some("co.uk"));
+/// assert_eq!(list.registrable_domain("www.example.co.uk"), Some("example.co.uk"));
+/// assert_eq!(list.public_suffix("a.b.ck"), Some("b.ck"));
+/// assert_eq!(list.public_suffix("www.ck"), Some("ck"));
+/// ```
+#[derive(Debug, Default)]
+pub struct PublicSuffixList {
+    rules: DomainLookupMap<Section>,
+}
+
+impl PublicSuffixList {
+    /// Returns a new, empty PublicSuffixList. Without any rules, the public suffix of every domain
+    /// is its top level domain.
+    pub fn new() -> PublicSuffixList {
+        PublicSuffixList {
+            rules: DomainLookupMap::new(),
+        }
+    }
+
+    /// Parses a list in the format of the official
+    /// [public_suffix_list.dat](https://publicsuffix.org/list/public_suffix_list.dat). Rules are
+    /// assigned to the section they appear in, as marked by the "===BEGIN ICANN DOMAINS===" and
+    /// "===BEGIN PRIVATE DOMAINS===" comments; rules before either marker count as ICANN rules.
+    ///
+    /// Internationalized rules such as "公司.cn" need the `idna` feature; without it they are
+    /// rejected like any other invalid rule, rather than leaving their suffixes out of the list.
+    /// Rules already in A-label form, such as "xn--55qx5d.cn", are accepted either way.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PslError`] for the first rule which is not a valid [`DomainPattern`](crate::DomainPattern).
+    pub fn parse(list: &str) -> Result<PublicSuffixList, PslError> {
+        let mut psl = PublicSuffixList::new();
+        let mut section = Section::Icann;
+
+        for (index, line) in list.lines().enumerate() {
+            let line = line.trim();
+            if let Some(comment) = line.strip_prefix("//") {
+                match comment.trim() {
+                    "===BEGIN ICANN DOMAINS===" => section = Section::Icann,
+                    "===BEGIN PRIVATE DOMAINS===" => section = Section::Private,
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Each line is only read up to the first whitespace
+            let rule = match line.split_whitespace().next() {
+                Some(rule) => rule,
+                None => continue,
+            };
+            match psl.insert(rule, section) {
+                Ok(_) => {}
+                Err(error) => {
+                    return Err(PslError {
+                        line: index + 1,
+                        rule: rule.to_owned(),
+                        error,
+                    })
+                }
+            }
+        }
+
+        Ok(psl)
+    }
+
+    /// Returns the list bundled with this crate, which is parsed on first use. Only available
+    /// with the `bundled-psl` feature.
+    ///
+    /// The bundled list is a snapshot and will go out of date; parse a fresh copy with
+    /// [`parse`](Self::parse) where that matters.
+    #[cfg(feature = "bundled-psl")]
+    pub fn bundled() -> std::sync::Arc<PublicSuffixList> {
+        static LIST: std::sync::OnceLock<std::sync::Arc<PublicSuffixList>> =
+            std::sync::OnceLock::new();
+
+        LIST.get_or_init(|| {
+            let list = PublicSuffixList::parse(include_str!("../data/public_suffix_list.dat"))
+                .expect("bundled public suffix list is invalid");
+            std::sync::Arc::new(list)
+        })
+        .clone()
+    }
+
+    /// Adds a rule to the list, returning whether it was newly added.
+    ///
+    /// # Arguments
+    ///
+    /// * `rule` - The rule in Public Suffix List syntax, e.g. "co.uk", "*.ck" or "!www.ck"
+    /// * `section` - The section of the list the rule belongs to
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PatternError`] if `rule` is not a valid [`DomainPattern`](crate::DomainPattern).
+    pub fn insert(&mut self, rule: &str, section: Section) -> Result<bool, PatternError> {
+        // Every rule covers the names below it, and an exception makes its own name registrable
+        let pattern = match rule.strip_prefix('!') {
+            Some(exception) => format!("!.{}", exception),
+            None => format!(".{}", rule),
+        };
+
+        self.rules
+            .insert(&pattern, section)
+            .map(|previous| previous.is_none())
+    }
+
+    /// Removes every rule from the private section, leaving only the ICANN rules.
+    pub fn without_private(mut self) -> PublicSuffixList {
+        let private = self
+            .rules
+            .iter()
+            .filter(|(_, section)| **section == Section::Private)
+            .map(|(rule, _)| rule)
+            .collect::<Vec<_>>();
+        for rule in private {
+            self.rules.remove(&rule);
+        }
+
+        self
+    }
+
+    /// Returns the public suffix of `domain`, or `None` if it isn't a valid name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::PublicSuffixList;
+    ///
+    /// let list = PublicSuffixList::parse("uk\nco.uk").unwrap();
+    /// assert_eq!(list.public_suffix("www.example.co.uk"), Some("co.uk"));
+    /// assert_eq!(list.public_suffix("example.com"), Some("com"));
+    /// ```
+    pub fn public_suffix<'a>(&self, domain: &'a str) -> Option<&'a str> {
+        let domain = strip_root(domain);
+        if domain.split('.').any(str::is_empty) {
+            return None;
+        }
+
+        last_labels(domain, self.suffix_len(domain))
+    }
+
+    /// Returns the registrable domain of `domain`, i.e. its public suffix and one more label, or
+    /// `None` if it is a public suffix itself or isn't a valid name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::PublicSuffixList;
+    ///
+    /// let list = PublicSuffixList::parse("uk\nco.uk").unwrap();
+    /// assert_eq!(list.registrable_domain("www.example.co.uk"), Some("example.co.uk"));
+    /// assert_eq!(list.registrable_domain("co.uk"), None);
+    /// ```
+    pub fn registrable_domain<'a>(&self, domain: &'a str) -> Option<&'a str> {
+        let domain = strip_root(domain);
+        if domain.split('.').any(str::is_empty) {
+            return None;
+        }
+
+        last_labels(domain, self.suffix_len(domain) + 1)
+    }
+
+    /// Returns the section of the rule which decides the public suffix of `domain`, or `None` if
+    /// no rule matches it.
+    pub fn section(&self, domain: &str) -> Option<Section> {
+        match self.rules.verdict(domain)? {
+            Verdict::Matched(m) | Verdict::Excluded(m) => Some(*m.value()),
+        }
+    }
+
+    /// Returns whether `domain` is a public suffix itself, such as "co.uk".
+    pub fn is_public_suffix(&self, domain: &str) -> bool {
+        self.public_suffix(domain) == Some(strip_root(domain))
+    }
+
+    /// Checks a rule against the list, rejecting wildcard rules whose name is a public suffix:
+    /// ".co.uk", "+.co.uk", "*.co.uk" and ".co.uk{1}" all match names registered by unrelated
+    /// parties. Exceptions and exact rules are accepted, as is a wildcard on a registrable domain
+    /// such as ".example.co.uk". An exact rule ending in a wildcard label, such as "*" or
+    /// "example.*", matches a name under every suffix though, so it is checked like a wildcard.
+    ///
+    /// A name with wildcard or glob labels is rejected if it could stand for a public suffix,
+    /// e.g. ".*.uk", ".c?.uk" or ".co.*" for "co.uk", or ".c*" for any top level domain. Exception
+    /// rules of the list are not taken into account there, so such names are rejected even if
+    /// the only suffix they could stand for is made registrable by an exception.
+    ///
+    /// Maps built with [`DomainLookupBuilder::public_suffixes`](crate::DomainLookupBuilder::public_suffixes)
+    /// run this check on every insert; call it directly to warn about such rules instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PatternError::PublicSuffixWildcard`] with the suffix the rule covers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use domain_lookup_tree::{DomainPattern, PatternError, PublicSuffixList};
+    ///
+    /// let list = PublicSuffixList::parse("uk\nco.uk").unwrap();
+    /// let rule: DomainPattern = "*.co.uk".parse().unwrap();
+    /// assert_eq!(
+    ///     list.check(&rule),
+    ///     Err(PatternError::PublicSuffixWildcard("co.uk".to_owned()))
+    /// );
+    /// assert!(list.check(&"*.example.co.uk".parse().unwrap()).is_ok());
+    /// assert!(list.check(&"co.uk".parse().unwrap()).is_ok());
+    /// ```
+    pub fn check(&self, pattern: &DomainPattern) -> Result<(), PatternError> {
+        let name = pattern.key();
+        let any_suffix = name.rsplit('.').next() == Some(WILDCARD_LABEL);
+        if pattern.is_exception() || (pattern.kind() == RuleKind::Exact && !any_suffix) {
+            return Ok(());
+        }
+
+        self.check_name(&name)
+    }
+
+    /// Checks the zone of a regex rule, which covers the names below it like a wildcard does.
+    #[cfg(feature = "regex")]
+    pub(crate) fn check_zone(&self, zone: &DomainPattern) -> Result<(), PatternError> {
+        if zone.is_exception() {
+            return Ok(());
+        }
+
+        self.check_name(&zone.key())
+    }
+
+    /// Rejects `name` if it is a public suffix or could stand for one.
+    fn check_name(&self, name: &str) -> Result<(), PatternError> {
+        let covered = if name.contains(['*', '?']) {
+            self.covered_suffix(name)
+        } else if self.is_public_suffix(name) {
+            Some(name.to_owned())
+        } else {
+            None
+        };
+        match covered {
+            Some(suffix) => Err(PatternError::PublicSuffixWildcard(suffix)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns a public suffix that `name`, which has wildcard or glob labels, could stand for.
+    /// If there are several, the first in alphabetical order is returned.
+    fn covered_suffix(&self, name: &str) -> Option<String> {
+        let labels = name.split('.').collect::<Vec<_>>();
+
+        let listed = self
+            .rules
+            .keys()
+            .filter_map(|rule| {
+                // Rules are stored as wildcards, and exceptions make names registrable
+                let rule = rule.strip_prefix('.')?;
+                instantiate(&labels, &rule.split('.').collect::<Vec<_>>())
+            })
+            // Prefer suffixes spelled out in full over ones with wildcard labels left, e.g. "*.ck"
+            .min_by(|a, b| (a.contains(WILDCARD_LABEL), a).cmp(&(b.contains(WILDCARD_LABEL), b)));
+        if listed.is_some() {
+            return listed;
+        }
+
+        // If no rule matches, the prevailing rule "*" makes every top level domain a suffix
+        match labels[..] {
+            [label] => Some(label.to_owned()),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of labels in the public suffix of `domain`.
+    fn suffix_len(&self, domain: &str) -> usize {
+        match self.rules.verdict(domain) {
+            Some(Verdict::Matched(m)) => m.depth(),
+            // An exception makes its own name registrable, so the suffix is its parent
+            Some(Verdict::Excluded(m)) => m.depth() - 1,
+            // If no rule matches, the prevailing rule is "*"
+            None => 1,
+        }
+    }
+}
+
+/// Returns the name both the labels of a rule, which may be wildcards or globs, and the labels of a
+/// list rule stand for, if there is one. A rightmost "*" in the rule stands for one or more labels.
+fn instantiate(labels: &[&str], rule: &[&str]) -> Option<String> {
+    let (labels, any_suffix) = match labels.split_last() {
+        Some((&WILDCARD_LABEL, left)) => (left, true),
+        _ => (labels, false),
+    };
+    if rule.len() < labels.len() || (rule.len() == labels.len()) == any_suffix {
+        return None;
+    }
+
+    let mut name = Vec::with_capacity(rule.len());
+    for (label, rule_label) in labels.iter().zip(rule) {
+        if *rule_label == WILDCARD_LABEL {
+            name.push(*label);
+        } else if glob::matches(label, rule_label) {
+            name.push(*rule_label);
+        } else {
+            return None;
+        }
+    }
+    name.extend_from_slice(&rule[labels.len()..]);
+
+    Some(name.join("."))
+}
+
+/// Returns the rightmost `count` labels of `domain`, or `None` if it doesn't have that many.
+fn last_labels(domain: &str, count: usize) -> Option<&str> {
+    let labels = domain.split('.').count();
+    if count == 0 || count > labels {
+        return None;
+    }
+    if count == labels {
+        return Some(domain);
+    }
+
+    let (dot, _) = domain.rmatch_indices('.').nth(count - 1)?;
+    Some(&domain[dot + 1..])
+}
@@ -0,0 +1,30 @@
+use regex::Regex;
+
+use crate::PatternError;
+
+/// Compiles the regex of a regex rule.
+pub(crate) fn compile(regex: &str) -> Result<Regex, PatternError> {
+    Regex::new(regex).map_err(|err| PatternError::InvalidRegex(err.to_string()))
+}
+
+/// Formats a regex rule attached to the zone `name`, e.g. "/^[a-z0-9]{16}$/.evil.net".
+pub(crate) fn rule_name(name: &str, regex: &Regex, exception: bool) -> String {
+    let exception = if exception { "!" } else { "" };
+    format!("{}/{}/.{}", exception, regex.as_str(), name)
+}
+
+/// Puts the reversed segments left below a node back together into the name regexes are matched
+/// against.
+pub(crate) fn unmatched(rest: &[&str]) -> String {
+    let mut labels = rest.iter().rev();
+    let mut unmatched = labels
+        .next()
+        .map(|label| label.to_string())
+        .unwrap_or_default();
+    for label in labels {
+        unmatched.push('.');
+        unmatched.push_str(label);
+    }
+
+    unmatched
+}
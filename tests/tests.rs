@@ -1,12 +1,17 @@
 extern crate domain_lookup_tree;
 
-use domain_lookup_tree::DomainLookupTree;
+use std::sync::Arc;
+
+use domain_lookup_tree::{
+	DepthLimit, DomainLookupBuilder, DomainLookupMap, DomainLookupTree, DomainPattern, FilterErrorKind, FilterList,
+	HostsErrorKind, PatternError, Profile, RuleKind, PublicSuffixList, Section, Verdict,
+};
 
 #[test]
 fn matches_wildcard_upper_level() {
 	let mut tree = DomainLookupTree::new();
 
-	tree.insert(".test.com");
+	tree.insert(".test.com").unwrap();
 
 	assert_eq!(tree.lookup("123.test.com"), Some(".test.com".to_string()))
 }
@@ -14,28 +19,28 @@ fn matches_wildcard_upper_level() {
 #[test]
 fn matches_wildcard_direct() {
 	let mut tree = DomainLookupTree::new();
-	tree.insert(".test.com");
+	tree.insert(".test.com").unwrap();
 	assert_eq!(tree.lookup("test.com"), Some(".test.com".to_string()))
 }
 
 #[test]
 fn does_not_match_noninserted() {
 	let mut tree = DomainLookupTree::new();
-	tree.insert(".test.com");
+	tree.insert(".test.com").unwrap();
 	assert_eq!(tree.lookup("google.com"), None)
 }
 
 #[test]
 fn matches_direct() {
 	let mut tree = DomainLookupTree::new();
-	tree.insert("test.com");
+	tree.insert("test.com").unwrap();
 	assert_eq!(tree.lookup("test.com"), Some("test.com".to_string()))
 }
 
 #[test]
 fn matches_wildcard_n_upper_level() {
 	let mut tree = DomainLookupTree::new();
-	tree.insert(".test.com");
+	tree.insert(".test.com").unwrap();
 
 	assert_eq!(
 		tree.lookup("a.b.c.123.test.com"),
@@ -46,12 +51,1020 @@ fn matches_wildcard_n_upper_level() {
 #[test]
 fn matches_multiple_inserts_under_common_gtld() {
 	let mut tree = DomainLookupTree::new();
-	tree.insert(".test.com");
-	tree.insert("google.com");
-	tree.insert("abc.com");
-	tree.insert("phineas.io");
+	tree.insert(".test.com").unwrap();
+	tree.insert("google.com").unwrap();
+	tree.insert("abc.com").unwrap();
+	tree.insert("phineas.io").unwrap();
 
 	assert_eq!(tree.lookup("google.com"), Some("google.com".to_string()));
 	assert_eq!(tree.lookup("phineas.io"), Some("phineas.io".to_string()));
 	assert_eq!(tree.lookup("test.com"), Some(".test.com".to_string()))
 }
+
+#[test]
+fn map_returns_value_of_most_specific_rule() {
+	let mut map = DomainLookupMap::new();
+	map.insert(".test.com", 1).unwrap();
+	map.insert("api.test.com", 2).unwrap();
+
+	assert_eq!(map.lookup("www.test.com"), Some(&1));
+	assert_eq!(map.lookup("api.test.com"), Some(&2));
+	assert_eq!(map.lookup("google.com"), None)
+}
+
+#[test]
+fn map_insert_replaces_value() {
+	let mut map = DomainLookupMap::new();
+	assert_eq!(map.insert(".test.com", "a"), Ok(None));
+	assert_eq!(map.insert(".test.com", "b"), Ok(Some("a")));
+	assert_eq!(map.lookup("test.com"), Some(&"b"))
+}
+
+#[test]
+fn removes_rules_independently() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".test.com").unwrap();
+	tree.insert("api.test.com").unwrap();
+
+	assert!(!tree.remove("test.com"));
+	assert!(tree.remove(".test.com"));
+	assert!(!tree.remove(".test.com"));
+	assert_eq!(tree.lookup("www.test.com"), None);
+	assert_eq!(tree.lookup("api.test.com"), Some("api.test.com".to_string()))
+}
+
+#[test]
+fn remove_prunes_empty_branches() {
+	let mut map = DomainLookupMap::new();
+	map.insert(".test.com", 2).unwrap();
+	map.insert("a.b.test.com", 1).unwrap();
+
+	assert_eq!(map.remove("a.b.test.com"), Some(1));
+	assert_eq!(map.lookup("a.b.test.com"), Some(&2));
+	assert_eq!(map.remove(".test.com"), Some(2));
+	assert!(map.traverse("test.com").is_none())
+}
+
+#[test]
+fn iterates_over_all_rules() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".test.com").unwrap();
+	tree.insert("a.b.test.com").unwrap();
+	tree.insert("phineas.io").unwrap();
+
+	let mut rules = tree.iter().collect::<Vec<_>>();
+	rules.sort();
+	assert_eq!(rules, vec![".test.com", "a.b.test.com", "phineas.io"]);
+
+	let mut owned = tree.into_iter().collect::<Vec<_>>();
+	owned.sort();
+	assert_eq!(owned, rules)
+}
+
+#[test]
+fn map_iterates_over_values() {
+	let mut map = DomainLookupMap::new();
+	map.insert(".test.com", 1).unwrap();
+	map.insert("phineas.io", 2).unwrap();
+
+	for (_, value) in &mut map {
+		*value *= 10;
+	}
+
+	let mut entries = map.into_iter().collect::<Vec<_>>();
+	entries.sort();
+	assert_eq!(
+		entries,
+		vec![(".test.com".to_string(), 10), ("phineas.io".to_string(), 20)]
+	)
+}
+
+#[test]
+fn enumerates_rules_under_zone() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".corp.test.com").unwrap();
+	tree.insert("a.b.corp.test.com").unwrap();
+	tree.insert("www.test.com").unwrap();
+	tree.insert("phineas.io").unwrap();
+
+	let mut rules = tree.rules_under(".corp.test.com").collect::<Vec<_>>();
+	rules.sort();
+	assert_eq!(rules, vec![".corp.test.com", "a.b.corp.test.com"]);
+	assert_eq!(tree.rules_under("b.corp.test.com").count(), 1);
+	assert_eq!(tree.rules_under("test.com").count(), 3);
+	assert_eq!(tree.rules_under("google.com").count(), 0)
+}
+
+#[test]
+fn checks_for_rules_under_zone() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("a.b.test.com").unwrap();
+
+	assert!(tree.has_rules_under("b.test.com"));
+	assert!(tree.has_rules_under("com"));
+	assert!(!tree.has_rules_under("c.test.com"));
+
+	tree.remove("a.b.test.com");
+	assert!(!tree.has_rules_under("com"))
+}
+
+#[test]
+fn lookup_all_returns_every_match_most_specific_first() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".test.com").unwrap();
+	tree.insert(".api.test.com").unwrap();
+	tree.insert("v1.api.test.com").unwrap();
+	tree.insert("v2.api.test.com").unwrap();
+
+	assert_eq!(
+		tree.lookup_all("v1.api.test.com"),
+		vec!["v1.api.test.com", ".api.test.com", ".test.com"]
+	);
+	assert_eq!(
+		tree.lookup_all("x.v1.api.test.com"),
+		vec![".api.test.com", ".test.com"]
+	);
+	assert!(tree.lookup_all("google.com").is_empty())
+}
+
+#[test]
+fn wildcard_after_exact_descendant_is_not_lost() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("a.test.com").unwrap();
+	tree.insert(".test.com").unwrap();
+
+	assert_eq!(tree.lookup("b.test.com"), Some(".test.com".to_string()));
+	assert_eq!(tree.lookup("a.test.com"), Some("a.test.com".to_string()))
+}
+
+#[test]
+fn stores_exact_and_wildcard_rules_on_same_name() {
+	let mut map = DomainLookupMap::new();
+	assert_eq!(map.insert("test.com", "apex"), Ok(None));
+	assert_eq!(map.insert(".test.com", "subdomains"), Ok(None));
+
+	assert_eq!(map.lookup("test.com"), Some(&"apex"));
+	assert_eq!(map.lookup("www.test.com"), Some(&"subdomains"));
+	assert_eq!(map.iter().count(), 2);
+
+	assert_eq!(map.remove("test.com"), Some("apex"));
+	assert_eq!(map.lookup("test.com"), Some(&"subdomains"))
+}
+
+#[test]
+fn insert_reports_whether_rule_was_new() {
+	let mut tree = DomainLookupTree::new();
+	assert!(tree.insert(".test.com").unwrap());
+	assert!(tree.insert("test.com").unwrap());
+	assert!(!tree.insert(".test.com").unwrap());
+	assert!(!tree.insert("test.com").unwrap())
+}
+
+#[test]
+fn match_describes_wildcard_hit() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".test.com").unwrap();
+
+	let m = tree.lookup_match("a.b.test.com").unwrap();
+	assert_eq!(m.rule(), ".test.com");
+	assert_eq!(m.kind(), RuleKind::Wildcard);
+	assert!(m.is_wildcard());
+	assert_eq!(m.depth(), 2);
+	assert_eq!(m.unmatched(), "a.b");
+
+	let apex = tree.lookup_match("test.com").unwrap();
+	assert_eq!(apex.kind(), RuleKind::Wildcard);
+	assert_eq!(apex.unmatched(), "")
+}
+
+#[test]
+fn match_describes_exact_hit() {
+	let mut map = DomainLookupMap::new();
+	map.insert("api.test.com", 7).unwrap();
+
+	let m = map.lookup_match("api.test.com").unwrap();
+	assert_eq!(m.rule(), "api.test.com");
+	assert_eq!(m.kind(), RuleKind::Exact);
+	assert_eq!(m.depth(), 3);
+	assert_eq!(m.unmatched(), "");
+	assert_eq!(m.value(), &7)
+}
+
+#[test]
+fn does_not_match_intermediate_labels() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("a.b.test.com").unwrap();
+
+	assert_eq!(tree.lookup("b.test.com"), None);
+	assert_eq!(tree.lookup("test.com"), None);
+	assert_eq!(tree.lookup("com"), None);
+	assert!(tree.traverse("b.test.com").is_none());
+	assert_eq!(tree.lookup("a.b.test.com"), Some("a.b.test.com".to_string()))
+}
+
+#[test]
+fn intermediate_labels_fall_back_to_wildcard() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".test.com").unwrap();
+	tree.insert("a.b.test.com").unwrap();
+
+	assert_eq!(tree.lookup("b.test.com"), Some(".test.com".to_string()));
+	let m = tree.lookup_match("b.test.com").unwrap();
+	assert_eq!(m.kind(), RuleKind::Wildcard);
+	assert_eq!(m.depth(), 2);
+	assert_eq!(tree.lookup_all("b.test.com"), vec![".test.com"])
+}
+
+#[test]
+fn reports_wildcard_rule_when_descending_past_it() {
+	let mut map = DomainLookupMap::new();
+	map.insert(".test.com", 1).unwrap();
+	map.insert("a.x.test.com", 2).unwrap();
+
+	let m = map.lookup_match("y.x.test.com").unwrap();
+	assert_eq!(m.rule(), ".test.com");
+	assert_eq!(m.unmatched(), "y.x");
+	assert_eq!(m.value(), &1);
+
+	let (fqdn, node) = map.traverse("y.x.test.com").unwrap();
+	assert_eq!(fqdn, "test.com");
+	assert_eq!(node.label(), "test");
+	assert_eq!(node.wildcard(), Some(&1))
+}
+
+#[test]
+fn rejects_invalid_patterns() {
+	let mut tree = DomainLookupTree::new();
+
+	assert_eq!(tree.insert(""), Err(PatternError::Empty));
+	assert_eq!(tree.insert("."), Err(PatternError::Empty));
+	assert_eq!(tree.insert("a..b.com"), Err(PatternError::EmptyLabel));
+	assert_eq!(tree.insert("..b.com"), Err(PatternError::EmptyLabel));
+	assert_eq!(
+		tree.insert(&format!("{}.com", "a".repeat(64))),
+		Err(PatternError::LabelTooLong("a".repeat(64)))
+	);
+	assert_eq!(
+		tree.insert(&format!("{}com", "abcdefgh.".repeat(32))),
+		Err(PatternError::NameTooLong(291))
+	);
+	assert_eq!(tree.insert("a b.com"), Err(PatternError::InvalidCharacter(' ')));
+	assert_eq!(tree.insert("a.b!.com"), Err(PatternError::InvalidCharacter('!')));
+	assert_eq!(tree.iter().count(), 0)
+}
+
+#[test]
+fn validates_with_selected_profile() {
+	let mut dns = DomainLookupTree::with_profile(Profile::Dns);
+	let mut strict = DomainLookupTree::with_profile(Profile::Hostname);
+
+	assert_eq!(dns.insert("_sip._tcp.test.com"), Ok(true));
+	assert_eq!(
+		strict.insert("_sip._tcp.test.com"),
+		Err(PatternError::InvalidCharacter('_'))
+	);
+	assert_eq!(strict.insert("-a.test.com"), Err(PatternError::InvalidCharacter('-')));
+	assert_eq!(strict.insert("a-b.test.com"), Ok(true))
+}
+
+#[test]
+fn pattern_round_trips_through_display() {
+	let pattern: DomainPattern = ".test.com".parse().unwrap();
+	assert_eq!(pattern.kind(), RuleKind::Wildcard);
+	assert_eq!(pattern.name(), "test.com");
+	assert_eq!(pattern.to_string(), ".test.com")
+}
+
+#[test]
+fn lookups_ignore_case_and_trailing_dot() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".google.com").unwrap();
+	tree.insert("Mail.Google.com.").unwrap();
+
+	assert_eq!(tree.lookup("WWW.Google.COM"), Some(".google.com".to_string()));
+	assert_eq!(tree.lookup("google.com."), Some(".google.com".to_string()));
+	assert_eq!(tree.lookup("MAIL.google.com."), Some("Mail.Google.com".to_string()));
+	assert_eq!(tree.insert("mail.GOOGLE.com"), Ok(false));
+	assert!(tree.has_rules_under("GOOGLE.com."));
+	assert!(tree.remove("MAIL.google.com."));
+	assert_eq!(tree.lookup("mail.google.com"), Some(".google.com".to_string()))
+}
+
+#[test]
+fn reports_rules_with_original_casing() {
+	let mut map = DomainLookupMap::new();
+	map.insert(".Example.ORG", 1).unwrap();
+
+	let m = map.lookup_match("WWW.example.org.").unwrap();
+	assert_eq!(m.rule(), ".Example.ORG");
+	assert_eq!(m.unmatched(), "WWW");
+	assert_eq!(map.keys().collect::<Vec<_>>(), vec![".Example.ORG"])
+}
+
+#[cfg(feature = "idna")]
+#[test]
+fn matches_internationalized_names_in_either_form() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".bücher.example").unwrap();
+	tree.insert("xn--mnchen-3ya.example").unwrap();
+
+	assert_eq!(
+		tree.lookup("www.xn--bcher-kva.example"),
+		Some(".xn--bcher-kva.example".to_string())
+	);
+	assert_eq!(
+		tree.lookup("WWW.BÜCHER.example"),
+		Some(".xn--bcher-kva.example".to_string())
+	);
+	assert_eq!(
+		tree.lookup("münchen.example."),
+		Some("xn--mnchen-3ya.example".to_string())
+	);
+	assert_eq!(tree.insert(".xn--bcher-kva.example"), Ok(false));
+
+	let mut rules = tree
+		.iter()
+		.map(|rule| domain_lookup_tree::to_unicode(&rule))
+		.collect::<Vec<_>>();
+	rules.sort();
+	assert_eq!(rules, vec![".bücher.example", "münchen.example"])
+}
+
+#[cfg(feature = "idna")]
+#[test]
+fn to_unicode_keeps_prefixes_and_limits() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".рф{1}").unwrap();
+	tree.insert("!пример.рф").unwrap();
+	tree.insert("*.пример.рф").unwrap();
+
+	let mut rules = tree
+		.iter()
+		.map(|rule| domain_lookup_tree::to_unicode(&rule))
+		.collect::<Vec<_>>();
+	rules.sort();
+	assert_eq!(rules, vec!["!пример.рф", "*.пример.рф", ".рф{1}"]);
+	assert_eq!(domain_lookup_tree::to_unicode(".xn--p1ai{1}"), ".рф{1}");
+	assert_eq!(domain_lookup_tree::to_unicode("!xn--p1ai"), "!рф");
+}
+
+#[cfg(not(feature = "idna"))]
+#[test]
+fn rejects_internationalized_names_without_idna() {
+	let mut tree = DomainLookupTree::new();
+	assert_eq!(tree.insert("bücher.example"), Err(PatternError::InvalidCharacter('ü')))
+}
+
+#[test]
+fn matches_embedded_wildcard_labels() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("api.*.test.com").unwrap();
+	tree.insert("*.cdn.*.test.net").unwrap();
+
+	assert_eq!(tree.lookup("api.eu.test.com"), Some("api.*.test.com".to_string()));
+	assert_eq!(tree.lookup("api.test.com"), None);
+	assert_eq!(tree.lookup("api.a.b.test.com"), None);
+	assert_eq!(
+		tree.lookup("img.cdn.us.test.net"),
+		Some("*.cdn.*.test.net".to_string())
+	);
+	assert_eq!(tree.lookup("cdn.us.test.net"), None)
+}
+
+#[test]
+fn backtracks_between_literal_and_wildcard_labels() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".eu.test.com").unwrap();
+	tree.insert("api.*.test.com").unwrap();
+
+	// The exact rule behind the wildcard label beats the wildcard rule on the literal path
+	assert_eq!(tree.lookup("api.eu.test.com"), Some("api.*.test.com".to_string()));
+	assert_eq!(tree.lookup("x.y.eu.test.com"), Some(".eu.test.com".to_string()));
+
+	// A single-label rule on the literal path is just as specific, and found first
+	tree.insert("*.eu.test.com").unwrap();
+	assert_eq!(tree.lookup("api.eu.test.com"), Some("*.eu.test.com".to_string()));
+	assert_eq!(
+		tree.lookup_all("api.eu.test.com"),
+		vec!["*.eu.test.com", "api.*.test.com", ".eu.test.com"]
+	)
+}
+
+#[test]
+fn prefers_literal_labels_among_equally_specific_rules() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("a.*.test.com").unwrap();
+	tree.insert("*.b.test.com").unwrap();
+
+	assert_eq!(tree.lookup("a.b.test.com"), Some("*.b.test.com".to_string()));
+	assert!(tree.remove("*.b.test.com"));
+	assert_eq!(tree.lookup("a.b.test.com"), Some("a.*.test.com".to_string()))
+}
+
+#[test]
+fn single_label_wildcard_excludes_apex_and_deeper_names() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("*.test.com").unwrap();
+
+	assert_eq!(tree.lookup("www.test.com"), Some("*.test.com".to_string()));
+	assert_eq!(tree.lookup("test.com"), None);
+	assert_eq!(tree.lookup("a.www.test.com"), None)
+}
+
+#[test]
+fn single_label_wildcard_coexists_with_any_depth_wildcard() {
+	let mut map = DomainLookupMap::new();
+	map.insert(".test.com", "any").unwrap();
+	map.insert("*.test.com", "one").unwrap();
+	map.insert("test.com", "apex").unwrap();
+
+	let m = map.lookup_match("www.test.com").unwrap();
+	assert_eq!(m.kind(), RuleKind::SingleLabel);
+	assert_eq!(m.rule(), "*.test.com");
+	assert_eq!(m.depth(), 3);
+	assert_eq!(m.unmatched(), "");
+	assert_eq!(m.value(), &"one");
+
+	assert_eq!(map.lookup_match("a.www.test.com").unwrap().kind(), RuleKind::Wildcard);
+	assert_eq!(map.lookup("test.com"), Some(&"apex"));
+	assert_eq!(
+		map.lookup_all("www.test.com")
+			.iter()
+			.map(|m| m.rule().to_string())
+			.collect::<Vec<_>>(),
+		vec!["*.test.com", ".test.com"]
+	);
+
+	let mut keys = map.keys().collect::<Vec<_>>();
+	keys.sort();
+	assert_eq!(keys, vec!["*.test.com", ".test.com", "test.com"]);
+
+	assert_eq!(map.remove("*.test.com"), Some("one"));
+	assert_eq!(map.lookup("www.test.com"), Some(&"any"))
+}
+
+#[test]
+fn subdomain_wildcard_excludes_apex() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("+.cdn.test.com").unwrap();
+	assert_eq!(tree.lookup("cdn.test.com"), None);
+	assert_eq!(tree.lookup("a.cdn.test.com"), Some("+.cdn.test.com".to_string()));
+	assert_eq!(tree.lookup("a.b.cdn.test.com"), Some("+.cdn.test.com".to_string()));
+	assert!(tree.traverse("cdn.test.com").is_none());
+}
+
+#[test]
+fn subdomain_wildcard_is_independent_of_wildcard() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("+.test.com").unwrap();
+	tree.insert(".test.com").unwrap();
+	assert_eq!(tree.lookup_all("test.com"), vec![".test.com"]);
+	assert_eq!(tree.lookup_all("www.test.com"), vec![".test.com", "+.test.com"]);
+
+	assert!(tree.remove(".test.com"));
+	assert_eq!(tree.lookup("test.com"), None);
+	assert_eq!(tree.iter().collect::<Vec<_>>(), vec!["+.test.com"]);
+}
+
+#[test]
+fn depth_limited_wildcard() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".test.com{1,2}").unwrap();
+	assert_eq!(tree.lookup("test.com"), None);
+	assert_eq!(tree.lookup("a.test.com"), Some(".test.com{1,2}".to_string()));
+	assert_eq!(tree.lookup("a.b.test.com"), Some(".test.com{1,2}".to_string()));
+	assert_eq!(tree.lookup("a.b.c.test.com"), None);
+	assert!(tree.traverse("a.b.c.test.com").is_none());
+}
+
+#[test]
+fn depth_limited_wildcard_is_preferred_over_unbounded() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".test.com").unwrap();
+	tree.insert(".test.com{,1}").unwrap();
+	assert_eq!(tree.lookup("a.test.com"), Some(".test.com{0,1}".to_string()));
+	assert_eq!(tree.lookup_all("a.b.test.com"), vec![".test.com"]);
+
+	assert!(tree.remove(".test.com{0,1}"));
+	assert_eq!(tree.iter().collect::<Vec<_>>(), vec![".test.com"]);
+}
+
+#[test]
+fn depth_limit_syntax() {
+	let pattern: DomainPattern = ".test.com{2}".parse().unwrap();
+	assert_eq!(pattern.limit(), DepthLimit::new(2, Some(2)));
+	assert_eq!(pattern.to_string(), ".test.com{2}");
+	assert_eq!(".test.com{0,}".parse::<DomainPattern>().unwrap().limit(), None);
+
+	for invalid in &[".test.com{2,1}", ".test.com{}", ".test.com{a}", "test.com{1}", ".test.com}"] {
+		assert_eq!(
+			invalid.parse::<DomainPattern>(),
+			Err(PatternError::InvalidDepthLimit),
+			"{}",
+			invalid
+		);
+	}
+}
+
+#[test]
+fn exception_carves_hole_into_wildcard() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".ads.test.com").unwrap();
+	tree.insert("!good.ads.test.com").unwrap();
+	assert_eq!(tree.lookup("bad.ads.test.com"), Some(".ads.test.com".to_string()));
+	assert_eq!(tree.lookup("good.ads.test.com"), None);
+	assert!(tree.traverse("good.ads.test.com").is_none());
+	assert!(tree.lookup_all("good.ads.test.com").is_empty());
+	// The exception is exact, so names below it are still matched
+	assert_eq!(tree.lookup("a.good.ads.test.com"), Some(".ads.test.com".to_string()));
+
+	let verdict = tree.verdict("good.ads.test.com").unwrap();
+	assert!(verdict.is_excluded());
+	assert_eq!(tree.verdict("test.com"), None);
+}
+
+#[test]
+fn more_specific_rule_wins_over_exception() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert(".ads.test.com").unwrap();
+	tree.insert("!.good.ads.test.com").unwrap();
+	tree.insert("tracker.good.ads.test.com").unwrap();
+	assert_eq!(tree.lookup("x.good.ads.test.com"), None);
+	assert_eq!(
+		tree.lookup_all("tracker.good.ads.test.com"),
+		vec!["tracker.good.ads.test.com"]
+	);
+	match tree.verdict("tracker.good.ads.test.com") {
+		Some(Verdict::Matched(m)) => assert_eq!(m.rule(), "tracker.good.ads.test.com"),
+		other => panic!("unexpected verdict {:?}", other),
+	}
+}
+
+#[test]
+fn wildcard_exception_overrides_shallower_single_label_rule() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("*.ads.test.com").unwrap();
+	tree.insert("!.good.ads.test.com").unwrap();
+	assert_eq!(tree.lookup("bad.ads.test.com"), Some("*.ads.test.com".to_string()));
+	assert_eq!(tree.lookup("good.ads.test.com"), None);
+	assert!(tree.verdict("good.ads.test.com").unwrap().is_excluded());
+	assert!(tree.lookup_all("good.ads.test.com").is_empty());
+
+	// An exact rule for the exception's own name still wins
+	tree.insert("good.ads.test.com").unwrap();
+	assert_eq!(tree.lookup("good.ads.test.com"), Some("good.ads.test.com".to_string()));
+
+	// Even over an exact exception
+	tree.insert("a.com").unwrap();
+	tree.insert("!a.com").unwrap();
+	assert_eq!(tree.lookup("a.com"), Some("a.com".to_string()));
+	assert_eq!(tree.lookup_all("a.com"), vec!["a.com"]);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn wildcard_exception_overrides_regex_rule() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert_regex("evil.net", r"^x").unwrap();
+	tree.insert("!.safe.evil.net").unwrap();
+	assert_eq!(tree.lookup("x.evil.net"), Some("/^x/.evil.net".to_string()));
+	assert_eq!(tree.lookup("x.safe.evil.net"), None);
+	assert!(tree.verdict("x.safe.evil.net").unwrap().is_excluded());
+
+	// An exception on the zone itself overrides its regex rules as well
+	tree.insert("!.evil.net").unwrap();
+	assert_eq!(tree.lookup("x.evil.net"), None);
+}
+
+#[test]
+fn exceptions_are_iterated_and_removed() {
+	let mut map = DomainLookupMap::new();
+	map.insert(".ads.test.com", 1).unwrap();
+	map.insert("!good.ads.test.com", 2).unwrap();
+
+	let mut rules = map.keys().collect::<Vec<_>>();
+	rules.sort();
+	assert_eq!(rules, vec!["!good.ads.test.com", ".ads.test.com"]);
+	assert_eq!(map.rules_under("ads.test.com").count(), 2);
+
+	assert_eq!(map.remove("good.ads.test.com"), None);
+	assert_eq!(map.remove("!good.ads.test.com"), Some(2));
+	assert_eq!(map.lookup("good.ads.test.com"), Some(&1));
+	assert!(!map.has_rules_under("good.ads.test.com"));
+}
+
+#[test]
+fn any_suffix_rule() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("test.*").unwrap();
+	assert_eq!(tree.lookup("test.com"), Some("test.*".to_string()));
+	assert_eq!(tree.lookup("test.co.uk"), Some("test.*".to_string()));
+	assert_eq!(tree.lookup("test.de."), Some("test.*".to_string()));
+	assert_eq!(tree.lookup("test"), None);
+	assert_eq!(tree.lookup("www.test.com"), None);
+	assert_eq!(tree.lookup("nottest.com"), None);
+}
+
+#[test]
+fn any_suffix_wildcard_rule() {
+	let mut map = DomainLookupMap::new();
+	map.insert(".test.*", "brand").unwrap();
+	let m = map.lookup_match("www.test.co.uk").unwrap();
+	assert_eq!(m.rule(), ".test.*");
+	assert_eq!(m.depth(), 3);
+	assert_eq!(m.unmatched(), "www");
+
+	// A rule matching at several suffix lengths is only reported once, with its deepest match
+	let matches = map.lookup_all("test.test.com");
+	assert_eq!(matches.len(), 1);
+	assert_eq!(matches[0].unmatched(), "");
+}
+
+#[test]
+fn any_suffix_rules_lose_ties_and_can_be_excepted() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("test.*").unwrap();
+	tree.insert("test.com").unwrap();
+	tree.insert("!test.org").unwrap();
+	assert_eq!(tree.lookup_all("test.com"), vec!["test.com", "test.*"]);
+	assert_eq!(tree.lookup("test.org"), None);
+	assert!(tree.remove("test.*"));
+	assert_eq!(tree.lookup("test.co.uk"), None);
+}
+
+#[test]
+fn glob_labels() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("cdn-*.test.com").unwrap();
+	tree.insert("node??.test.com").unwrap();
+	assert_eq!(tree.lookup("cdn-01.test.com"), Some("cdn-*.test.com".to_string()));
+	assert_eq!(tree.lookup("CDN-eu-7.test.com"), Some("cdn-*.test.com".to_string()));
+	assert_eq!(tree.lookup("cdn.test.com"), None);
+	assert_eq!(tree.lookup("node42.test.com"), Some("node??.test.com".to_string()));
+	assert_eq!(tree.lookup("node4.test.com"), None);
+	assert_eq!(tree.lookup("a.cdn-01.test.com"), None);
+	assert_eq!(tree.lookup_all("cdn-*.test.com"), vec!["cdn-*.test.com"]);
+}
+
+#[test]
+fn glob_label_precedence() {
+	let mut tree = DomainLookupTree::new();
+	tree.insert("*.*.test.com").unwrap();
+	tree.insert("*.cdn-*.test.com").unwrap();
+	tree.insert("*.cdn-eu-*.test.com").unwrap();
+	tree.insert("*.cdn-eu-1.test.com").unwrap();
+	assert_eq!(tree.lookup("a.cdn-eu-1.test.com"), Some("*.cdn-eu-1.test.com".to_string()));
+	assert_eq!(tree.lookup("a.cdn-eu-2.test.com"), Some("*.cdn-eu-*.test.com".to_string()));
+	assert_eq!(tree.lookup("a.cdn-us-2.test.com"), Some("*.cdn-*.test.com".to_string()));
+	assert_eq!(tree.lookup("a.www.test.com"), Some("*.*.test.com".to_string()));
+
+	assert!(tree.remove("*.cdn-eu-*.test.com"));
+	assert_eq!(tree.lookup("a.cdn-eu-2.test.com"), Some("*.cdn-*.test.com".to_string()));
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn regex_rules_match_labels_below_their_zone() {
+	let mut map = DomainLookupMap::new();
+	map.insert_regex("evil.net", r"^[a-z0-9]{16}$", "dga").unwrap();
+	map.insert(".evil.net", "zone").unwrap();
+
+	let m = map.lookup_match("0123456789ABCDEF.evil.net").unwrap();
+	assert_eq!(m.rule(), "/^[a-z0-9]{16}$/.evil.net");
+	assert_eq!(m.kind(), RuleKind::Regex);
+	assert_eq!(m.unmatched(), "0123456789ABCDEF");
+	assert_eq!(map.lookup("www.evil.net"), Some(&"zone"));
+	assert_eq!(map.lookup("evil.net"), Some(&"zone"));
+	// The regex only sees the labels below its zone
+	assert_eq!(map.lookup("0123456789abcdef.other.net"), None);
+	assert_eq!(map.lookup_all("0123456789abcdef.evil.net").len(), 2);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn regex_rules_are_iterated_removed_and_validated() {
+	let mut tree = DomainLookupTree::new();
+	assert_eq!(tree.insert_regex("evil.net", r"^x\d+$"), Ok(true));
+	assert_eq!(tree.insert_regex("evil.net", r"^x\d+$"), Ok(false));
+	assert_eq!(tree.insert_regex("!evil.net", r"^x1$"), Ok(true));
+	assert_eq!(tree.lookup("x2.evil.net"), Some(r"/^x\d+$/.evil.net".to_string()));
+	assert_eq!(tree.lookup("x1.evil.net"), None);
+
+	let mut rules = tree.iter().collect::<Vec<_>>();
+	rules.sort();
+	assert_eq!(rules, vec![r"!/^x1$/.evil.net", r"/^x\d+$/.evil.net"]);
+
+	assert!(matches!(tree.insert_regex("evil.net", "("), Err(PatternError::InvalidRegex(_))));
+	assert!(matches!(tree.insert_regex(".evil.net", "x"), Err(PatternError::InvalidRegex(_))));
+
+	assert!(tree.remove_regex("evil.net", r"^x\d+$"));
+	assert!(tree.remove_regex("!evil.net", r"^x1$"));
+	assert!(!tree.has_rules_under("evil.net"));
+}
+
+const PSL: &str = "// ===BEGIN ICANN DOMAINS===
+com
+uk
+co.uk
+*.ck
+!www.ck
+// ===END ICANN DOMAINS===
+// ===BEGIN PRIVATE DOMAINS===
+github.io  some trailing text
+// ===END PRIVATE DOMAINS===
+";
+
+#[test]
+fn public_suffix_list_rules() {
+	let list = PublicSuffixList::parse(PSL).unwrap();
+	assert_eq!(list.public_suffix("www.Example.CO.uk."), Some("CO.uk"));
+	assert_eq!(list.registrable_domain("www.Example.CO.uk."), Some("Example.CO.uk"));
+	assert_eq!(list.public_suffix("a.b.c.ck"), Some("c.ck"));
+	assert_eq!(list.registrable_domain("a.b.c.ck"), Some("b.c.ck"));
+	assert_eq!(list.public_suffix("a.www.ck"), Some("ck"));
+	assert_eq!(list.registrable_domain("a.www.ck"), Some("www.ck"));
+	// The implicit "*" rule applies to unlisted top level domains
+	assert_eq!(list.public_suffix("example.test"), Some("test"));
+	assert_eq!(list.registrable_domain("com"), None);
+	assert_eq!(list.public_suffix("a..com"), None);
+}
+
+#[test]
+fn public_suffix_list_sections() {
+	let list = PublicSuffixList::parse(PSL).unwrap();
+	assert_eq!(list.section("me.github.io"), Some(Section::Private));
+	assert_eq!(list.section("example.co.uk"), Some(Section::Icann));
+	assert_eq!(list.section("example.test"), None);
+	assert_eq!(list.registrable_domain("www.me.github.io"), Some("me.github.io"));
+
+	let icann = list.without_private();
+	assert_eq!(icann.registrable_domain("www.me.github.io"), Some("github.io"));
+}
+
+#[test]
+fn public_suffix_list_reports_invalid_rules() {
+	let err = PublicSuffixList::parse("com\n\n// comment\nfoo..com\n").unwrap_err();
+	assert_eq!(err.line(), 4);
+	assert_eq!(err.rule(), "foo..com");
+	assert_eq!(err.error(), &PatternError::EmptyLabel);
+}
+
+#[cfg(not(feature = "idna"))]
+#[test]
+fn public_suffix_list_without_idna() {
+	let list = PublicSuffixList::parse("cn\nxn--55qx5d.cn").unwrap();
+	assert_eq!(list.registrable_domain("www.example.xn--55qx5d.cn"), Some("example.xn--55qx5d.cn"));
+	assert_eq!(list.registrable_domain("xn--55qx5d.cn"), None);
+
+	// Rules in U-label form can't be stored, and are not silently left out
+	let err = PublicSuffixList::parse("cn\n公司.cn").unwrap_err();
+	assert_eq!(err.line(), 2);
+	assert_eq!(err.error(), &PatternError::InvalidCharacter('公'));
+}
+
+#[test]
+fn minimum_level() {
+	let mut map = DomainLookupBuilder::new().minimum_level(2).build();
+	assert_eq!(map.minimum_level(), 2);
+	let too_short = Err(PatternError::BelowMinimumLevel { labels: 1, minimum: 2 });
+	assert_eq!(map.insert("com", 1), too_short);
+	assert_eq!(map.insert(".com.", 1), too_short);
+	assert_eq!(map.insert("*.com", 1), too_short);
+	assert_eq!(map.insert("!com", 1), too_short);
+	assert_eq!(map.insert("example.*", 1), Ok(None));
+	assert_eq!(map.insert(".example.com", 2), Ok(None));
+
+	assert_eq!(map.lookup("com"), None);
+	assert_eq!(map.try_lookup("com."), Err(PatternError::BelowMinimumLevel { labels: 1, minimum: 2 }));
+	assert_eq!(map.try_lookup("www.example.com"), Ok(Some(&2)));
+	assert_eq!(map.try_lookup("example.net"), Ok(Some(&1)));
+	assert_eq!(map.try_lookup("example.org.uk"), Ok(Some(&1)));
+	assert_eq!(map.try_lookup("other.com"), Ok(None));
+
+	// The default minimum level of 0 accepts everything
+	let mut tree = DomainLookupTree::new();
+	assert_eq!(tree.insert(".com"), Ok(true));
+	assert_eq!(tree.try_lookup("com"), Ok(Some(".com".to_string())));
+}
+
+#[test]
+fn hosts_file_import() {
+	let hosts = b"# Blocklist\r
+127.0.0.1\tlocalhost localhost.localdomain\r
+::1 localhost ip6-localhost ip6-loopback\r
+fe80::1%lo0 localhost
+0.0.0.0 0.0.0.0
+\t
+0.0.0.0 ads.example.com # inline comment
+0.0.0.0  Tracker.Example.NET\tpixel.example.net metrics.example.org
+127.0.0.1 a..b.com good.example.com *.example.com
+ads.example.com
+0.0.0.0
+0.0.0.0 \xff.example.com
+::  ipv6.example.com";
+
+	let mut tree = DomainLookupTree::new();
+	let errors = tree.load_hosts(&hosts[..]).unwrap();
+	let diagnostics = errors.iter().map(|e| (e.line(), e.entry(), e.kind().clone())).collect::<Vec<_>>();
+	assert_eq!(
+		diagnostics,
+		vec![
+			(9, "a..b.com", HostsErrorKind::InvalidHostname(PatternError::EmptyLabel)),
+			(9, "*.example.com", HostsErrorKind::InvalidHostname(PatternError::InvalidCharacter('*'))),
+			(10, "ads.example.com", HostsErrorKind::InvalidAddress),
+			(11, "0.0.0.0", HostsErrorKind::MissingHostname),
+			(12, "0.0.0.0 \u{fffd}.example.com", HostsErrorKind::InvalidUtf8),
+		]
+	);
+
+	assert_eq!(tree.iter().count(), 6);
+	for name in &["ads.example.com", "good.example.com", "ipv6.example.com", "pixel.example.net", "tracker.example.net"] {
+		assert!(tree.lookup(name).is_some(), "{}", name);
+	}
+	assert_eq!(tree.lookup("www.ads.example.com"), None);
+	assert_eq!(tree.lookup("localhost"), None);
+	assert_eq!(tree.lookup("0.0.0.0"), None);
+}
+
+#[test]
+fn adblock_filter_list() {
+	let list = "[Adblock Plus 2.0]
+! Title: Example list
+# AdGuard style comment
+
+||ads.example.com^
+||tracker.example.net^|
+@@||good.ads.example.com^
+||cdn-*.example.org^
+example.com##.banner
+##.banner
+#@#.ad
+||example.com/ads/*
+||popup.example.com^$popup,third-party
+||a..b.com^
+/^ads[0-9]+\\./
+";
+
+	let mut filters = FilterList::new();
+	let errors = filters.load(list.as_bytes()).unwrap();
+	let diagnostics = errors.iter().map(|e| (e.line(), e.filter(), e.kind().clone())).collect::<Vec<_>>();
+	let mut expected = vec![
+		(9, "example.com##.banner", FilterErrorKind::Unsupported),
+		(10, "##.banner", FilterErrorKind::Unsupported),
+		(11, "#@#.ad", FilterErrorKind::Unsupported),
+		(12, "||example.com/ads/*", FilterErrorKind::Unsupported),
+		(13, "||popup.example.com^$popup,third-party", FilterErrorKind::UnsupportedModifier("popup".to_owned())),
+		(14, "||a..b.com^", FilterErrorKind::InvalidPattern(PatternError::EmptyLabel)),
+	];
+	if cfg!(not(feature = "regex")) {
+		expected.push((15, "/^ads[0-9]+\\./", FilterErrorKind::Unsupported));
+	}
+	assert_eq!(diagnostics, expected);
+
+	assert_eq!(filters.verdict("www.ads.example.com"), Some(Verdict::Matched("||ads.example.com^")));
+	assert_eq!(filters.verdict("ads.example.com."), Some(Verdict::Matched("||ads.example.com^")));
+	assert_eq!(filters.verdict("Tracker.Example.NET"), Some(Verdict::Matched("||tracker.example.net^|")));
+	assert_eq!(filters.verdict("x.good.ads.example.com"), Some(Verdict::Excluded("@@||good.ads.example.com^")));
+	assert!(filters.is_blocked("cdn-1.example.org"));
+	assert!(!filters.is_blocked("cdn.example.org"));
+	assert!(!filters.is_blocked("popup.example.com"));
+	assert_eq!(filters.verdict("example.com"), None);
+
+	assert_eq!(filters.insert("||ads.example.com^"), Ok(false));
+	assert_eq!(filters.insert("! comment"), Ok(false));
+	assert_eq!(filters.insert("||example.com^$"), Err(FilterErrorKind::UnsupportedModifier(String::new())));
+	assert_eq!(filters.insert("example.com"), Err(FilterErrorKind::Unsupported));
+}
+
+#[test]
+fn adblock_exceptions_and_important() {
+	let mut filters = FilterList::new();
+	filters.insert("||example.com^").unwrap();
+	filters.insert("||ads.example.com^").unwrap();
+	// Unlike "!" rules, an exception wins even over a more specific block filter
+	filters.insert("@@||example.com^").unwrap();
+	assert_eq!(filters.verdict("ads.example.com"), Some(Verdict::Excluded("@@||example.com^")));
+
+	filters.insert("||ads.example.com^$important").unwrap();
+	assert_eq!(filters.verdict("ads.example.com"), Some(Verdict::Matched("||ads.example.com^$important")));
+	assert_eq!(filters.verdict("www.example.com"), Some(Verdict::Excluded("@@||example.com^")));
+
+	filters.insert("@@||safe.ads.example.com^$important").unwrap();
+	assert_eq!(
+		filters.verdict("safe.ads.example.com"),
+		Some(Verdict::Excluded("@@||safe.ads.example.com^$important"))
+	);
+	// An important exception with no block filter to override decides nothing
+	filters.insert("@@||example.net^$important").unwrap();
+	assert_eq!(filters.verdict("example.net"), None);
+}
+
+#[test]
+fn adblock_filters_are_validated_with_builder() {
+	let list = Arc::new(PublicSuffixList::parse(PSL).unwrap());
+	let mut filters = FilterList::with_builder(DomainLookupBuilder::new().public_suffixes(list));
+	let errors = filters.load("||co.uk^\n@@||github.io^$important\n||bbc.co.uk^\n".as_bytes()).unwrap();
+	let diagnostics = errors.iter().map(|e| (e.line(), e.kind().clone())).collect::<Vec<_>>();
+	let suffix = |suffix: &str| FilterErrorKind::InvalidPattern(PatternError::PublicSuffixWildcard(suffix.to_owned()));
+	assert_eq!(diagnostics, vec![(1, suffix("co.uk")), (2, suffix("github.io"))]);
+	assert!(filters.is_blocked("www.bbc.co.uk"));
+	assert!(!filters.is_blocked("example.co.uk"));
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn adblock_regex_filters() {
+	let mut filters = FilterList::new();
+	filters.insert("/^ads[0-9]+\\./").unwrap();
+	filters.insert("@@/^ads1\\.safe\\.com$/").unwrap();
+	filters.insert("/^(tracker|pixel)\\.example\\.net$/$important").unwrap();
+	assert!(filters.is_blocked("ads12.example.com"));
+	assert!(filters.is_blocked("ADS2.example.com."));
+	assert!(!filters.is_blocked("ads.example.com"));
+	assert_eq!(filters.verdict("ads1.safe.com"), Some(Verdict::Excluded("@@/^ads1\\.safe\\.com$/")));
+	assert_eq!(
+		filters.verdict("pixel.example.net"),
+		Some(Verdict::Matched("/^(tracker|pixel)\\.example\\.net$/$important"))
+	);
+	assert!(matches!(filters.insert("/(/"), Err(FilterErrorKind::InvalidPattern(PatternError::InvalidRegex(_)))));
+}
+
+#[test]
+fn public_suffix_wildcards_are_rejected() {
+	let list = Arc::new(PublicSuffixList::parse(PSL).unwrap());
+	let mut map = DomainLookupBuilder::new().public_suffixes(list.clone()).build();
+	for (rule, suffix) in &[
+		(".co.uk", "co.uk"),
+		("+.CO.UK.", "co.uk"),
+		("*.co.uk", "co.uk"),
+		(".co.uk{1}", "co.uk"),
+		(".github.io", "github.io"),
+		(".x.ck", "x.ck"),
+		(".test", "test"),
+		// Wildcard and glob labels are rejected if they could stand for a public suffix
+		(".*.uk", "co.uk"),
+		("+.*.uk", "co.uk"),
+		("*.*.uk", "co.uk"),
+		(".c?.uk", "co.uk"),
+		("+.co*.uk", "co.uk"),
+		(".co.*", "co.ck"),
+		(".c*", "com"),
+		(".z*", "z*"),
+		// An exact rule ending in a wildcard label matches a name under every suffix
+		("*", "co.uk"),
+	] {
+		assert_eq!(map.insert(rule, 1), Err(PatternError::PublicSuffixWildcard(suffix.to_string())), "{}", rule);
+	}
+	assert_eq!(map.insert("co.uk", 1), Ok(None));
+	assert_eq!(map.insert("!.co.uk", 1), Ok(None));
+	assert_eq!(map.insert(".example.co.uk", 1), Ok(None));
+	assert_eq!(map.insert(".www.ck", 1), Ok(None));
+	assert_eq!(map.insert(".*.example.co.uk", 1), Ok(None));
+	assert_eq!(map.insert("+.ex?mple.co.uk", 1), Ok(None));
+	assert_eq!(map.iter().count(), 6);
+
+	// A regex rule covers the names below its zone
+	#[cfg(feature = "regex")]
+	{
+		assert_eq!(map.insert_regex("co.uk", "^[^.]+$", 1), Err(PatternError::PublicSuffixWildcard("co.uk".to_owned())));
+		assert_eq!(map.insert_regex("example.co.uk", "^[^.]+$", 1), Ok(None));
+	}
+
+	// Without the private section, wildcards on private suffixes are allowed
+	let icann = Arc::new(PublicSuffixList::parse(PSL).unwrap().without_private());
+	let mut tree = DomainLookupBuilder::new().public_suffixes(icann).build_tree();
+	assert_eq!(tree.insert(".github.io"), Ok(true));
+	assert_eq!(tree.insert(".io"), Err(PatternError::PublicSuffixWildcard("io".to_owned())));
+
+	// Checking a rule directly leaves it to the caller whether to insert it
+	assert!(list.check(&"*.co.uk".parse().unwrap()).is_err());
+	assert!(DomainLookupTree::new().insert("*.co.uk").is_ok());
+}
+
+#[cfg(feature = "bundled-psl")]
+#[test]
+fn bundled_public_suffix_list() {
+	let list = PublicSuffixList::bundled();
+	// From the test vectors published alongside the list
+	assert_eq!(list.registrable_domain("a.b.example.com"), Some("example.com"));
+	assert_eq!(list.registrable_domain("b.example.uk.com"), Some("example.uk.com"));
+	assert_eq!(list.registrable_domain("test.ac"), Some("test.ac"));
+	assert_eq!(list.registrable_domain("mm"), None);
+	assert_eq!(list.registrable_domain("c.mm"), None);
+	assert_eq!(list.registrable_domain("a.b.c.mm"), Some("b.c.mm"));
+	assert_eq!(list.registrable_domain("a.b.test.ck"), Some("b.test.ck"));
+	assert_eq!(list.registrable_domain("www.www.ck"), Some("www.ck"));
+	assert_eq!(list.registrable_domain("a.b.city.kobe.jp"), Some("city.kobe.jp"));
+	assert_eq!(list.registrable_domain("www.city.kobe.jp"), Some("city.kobe.jp"));
+	assert_eq!(list.registrable_domain("k12.ak.us"), None);
+	assert_eq!(list.registrable_domain("www.test.k12.ak.us"), Some("test.k12.ak.us"));
+}
+
+#[cfg(feature = "bundled-psl")]
+#[test]
+fn bundled_public_suffix_list_internationalized() {
+	let list = PublicSuffixList::bundled();
+	assert_eq!(list.registrable_domain("www.食狮.公司.cn"), Some("食狮.公司.cn"));
+	assert_eq!(list.registrable_domain("www.xn--85x722f.xn--55qx5d.cn"), Some("xn--85x722f.xn--55qx5d.cn"));
+	assert_eq!(list.registrable_domain("公司.cn"), None);
+}